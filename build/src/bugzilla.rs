@@ -0,0 +1,369 @@
+use crate::network::get;
+use anyhow::Result;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::env;
+use url::Url;
+
+/// Maximum number of ids or aliases to put in a single request
+const BATCH_SIZE: usize = 100;
+
+/// Number of results to request per page when searching
+const PAGE_SIZE: usize = 500;
+
+/// A bug as returned by the REST API.
+///
+/// Only the fields that were requested are filled in, the rest are left
+/// at their default values.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Bug {
+    pub id: u64,
+    pub alias: Option<String>,
+    pub summary: String,
+    pub product: String,
+    pub component: String,
+    pub status: String,
+    pub resolution: String,
+    pub assigned_to: String,
+    pub priority: String,
+    pub severity: String,
+    pub depends_on: Vec<u64>,
+    pub cf_user_story: String,
+}
+
+impl Bug {
+    pub fn alias(&self) -> &str {
+        self.alias.as_deref().unwrap_or_default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        !matches!(self.status.as_ref(), "RESOLVED" | "VERIFIED" | "CLOSED")
+    }
+
+    pub fn is_fixed(&self) -> bool {
+        !self.is_open() && self.resolution == "FIXED"
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BugResponse {
+    bugs: Vec<Bug>,
+}
+
+/// A bug search; empty criteria are ignored
+#[derive(Debug, Default)]
+pub struct BugQuery {
+    ids: Vec<u64>,
+    aliases: Vec<String>,
+    products: Vec<String>,
+    components: Vec<String>,
+    fields: Vec<String>,
+    open_only: bool,
+}
+
+impl BugQuery {
+    pub fn new() -> BugQuery {
+        Default::default()
+    }
+
+    pub fn add_id(&mut self, id: u64) {
+        self.ids.push(id);
+    }
+
+    pub fn add_alias(&mut self, alias: &str) {
+        self.aliases.push(alias.into());
+    }
+
+    // Component searches aren't used by the build yet
+    #[allow(dead_code)]
+    pub fn add_product(&mut self, product: &str) {
+        self.products.push(product.into());
+    }
+
+    #[allow(dead_code)]
+    pub fn add_component(&mut self, component: &str) {
+        self.components.push(component.into());
+    }
+
+    pub fn add_field(&mut self, field: &str) {
+        self.fields.push(field.into());
+    }
+
+    /// Only match bugs that haven't been resolved
+    #[allow(dead_code)]
+    pub fn set_open_only(&mut self) {
+        self.open_only = true;
+    }
+
+    fn url(&self, base_url: &Url, limit: usize, offset: usize) -> Result<Url> {
+        let mut url = base_url.join("rest/bug")?;
+        {
+            let mut query = url.query_pairs_mut();
+            if !self.ids.is_empty() {
+                let ids = self.ids.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+                query.append_pair("id", &ids.join(","));
+            }
+            if !self.aliases.is_empty() {
+                query.append_pair("alias", &self.aliases.join(","));
+            }
+            for product in self.products.iter() {
+                query.append_pair("product", product);
+            }
+            for component in self.components.iter() {
+                query.append_pair("component", component);
+            }
+            if self.open_only {
+                query.append_pair("resolution", "---");
+            }
+            if !self.fields.is_empty() {
+                query.append_pair("include_fields", &self.fields.join(","));
+            }
+            query.append_pair("limit", &limit.to_string());
+            query.append_pair("offset", &offset.to_string());
+        }
+        Ok(url)
+    }
+}
+
+/// Client for the Bugzilla REST API
+pub struct Bugzilla<'a> {
+    client: &'a reqwest::blocking::Client,
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl<'a> Bugzilla<'a> {
+    pub fn new(
+        client: &'a reqwest::blocking::Client,
+        base_url: Url,
+        api_key: Option<String>,
+    ) -> Bugzilla<'a> {
+        Bugzilla {
+            client,
+            base_url,
+            api_key,
+        }
+    }
+
+    /// Create a client for bugzilla.mozilla.org.
+    ///
+    /// BUGZILLA_URL overrides the server e.g. to use a local stand-in, and
+    /// BUGZILLA_API_KEY is sent with each request if it's set.
+    pub fn from_env(client: &'a reqwest::blocking::Client) -> Result<Bugzilla<'a>> {
+        let mut base_url =
+            env::var("BUGZILLA_URL").unwrap_or_else(|_| "https://bugzilla.mozilla.org/".into());
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Ok(Bugzilla::new(
+            client,
+            Url::parse(&base_url)?,
+            env::var("BUGZILLA_API_KEY").ok(),
+        ))
+    }
+
+    fn headers(&self) -> Result<Option<reqwest::header::HeaderMap>> {
+        Ok(match self.api_key {
+            Some(ref api_key) => {
+                let mut headers = reqwest::header::HeaderMap::new();
+                headers.insert("X-BUGZILLA-API-KEY", api_key.parse()?);
+                Some(headers)
+            }
+            None => None,
+        })
+    }
+
+    /// Get all the bugs matching a query, following pages of results
+    pub fn search(&self, query: &BugQuery) -> Result<Vec<Bug>> {
+        let mut bugs = Vec::new();
+        loop {
+            let url = query.url(&self.base_url, PAGE_SIZE, bugs.len())?;
+            let resp = get(self.client, url.as_str(), self.headers()?)?;
+            let page: BugResponse = serde_json::from_str(&resp)?;
+            let page_len = page.bugs.len();
+            bugs.extend(page.bugs);
+            if page_len < PAGE_SIZE {
+                break;
+            }
+            debug!("Fetched {} bugs, getting next page", bugs.len());
+        }
+        Ok(bugs)
+    }
+
+    /// Get bugs by id, splitting long lists of ids into several requests
+    pub fn bugs_by_id(&self, ids: &[u64], fields: &[&str]) -> Result<Vec<Bug>> {
+        let mut bugs = Vec::with_capacity(ids.len());
+        for batch in ids.chunks(BATCH_SIZE) {
+            let mut query = BugQuery::new();
+            for id in batch {
+                query.add_id(*id);
+            }
+            for field in fields {
+                query.add_field(field);
+            }
+            bugs.extend(self.search(&query)?);
+        }
+        Ok(bugs)
+    }
+
+    /// Get bugs by alias, splitting long lists of aliases into several requests
+    pub fn bugs_by_alias(&self, aliases: &[String], fields: &[&str]) -> Result<Vec<Bug>> {
+        let mut bugs = Vec::with_capacity(aliases.len());
+        for batch in aliases.chunks(BATCH_SIZE) {
+            let mut query = BugQuery::new();
+            for alias in batch {
+                query.add_alias(alias);
+            }
+            for field in fields {
+                query.add_field(field);
+            }
+            bugs.extend(self.search(&query)?);
+        }
+        Ok(bugs)
+    }
+
+    /// Get the bugs in a product and component
+    #[allow(dead_code)]
+    pub fn bugs_in_component(
+        &self,
+        product: &str,
+        component: &str,
+        open_only: bool,
+        fields: &[&str],
+    ) -> Result<Vec<Bug>> {
+        let mut query = BugQuery::new();
+        query.add_product(product);
+        query.add_component(component);
+        if open_only {
+            query.set_open_only();
+        }
+        for field in fields {
+            query.add_field(field);
+        }
+        self.search(&query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Response, TestServer};
+
+    fn bugs_json(ids: impl Iterator<Item = u64>) -> String {
+        let bugs = ids
+            .map(|id| format!(r#"{{"id": {}, "status": "NEW"}}"#, id))
+            .collect::<Vec<_>>();
+        format!(r#"{{"bugs": [{}]}}"#, bugs.join(","))
+    }
+
+    fn bugzilla<'a>(
+        client: &'a reqwest::blocking::Client,
+        server: &TestServer,
+        api_key: Option<&str>,
+    ) -> Bugzilla<'a> {
+        let _env = test_server::lock_env();
+        env::set_var("BUGZILLA_URL", server.url());
+        match api_key {
+            Some(api_key) => env::set_var("BUGZILLA_API_KEY", api_key),
+            None => env::remove_var("BUGZILLA_API_KEY"),
+        }
+        Bugzilla::from_env(client).unwrap()
+    }
+
+    #[test]
+    fn bugs_by_id_batches_ids() {
+        let server = TestServer::start(|request| {
+            let ids = request
+                .query("id")
+                .unwrap_or_default()
+                .split(',')
+                .filter_map(|id| id.parse().ok());
+            Response::json(&bugs_json(ids))
+        });
+        let client = test_server::client();
+        let bugzilla = bugzilla(&client, &server, None);
+
+        let ids = (1..=250).collect::<Vec<u64>>();
+        let bugs = bugzilla.bugs_by_id(&ids, &["id", "status"]).unwrap();
+        assert_eq!(bugs.iter().map(|x| x.id).collect::<Vec<_>>(), ids);
+        assert!(bugs.iter().all(|x| x.is_open()));
+
+        let requests = server.requests();
+        let batches = requests
+            .iter()
+            .map(|x| x.query("id").unwrap().split(',').count())
+            .collect::<Vec<_>>();
+        assert_eq!(batches, [100, 100, 50]);
+        assert_eq!(
+            requests[1].query("id").unwrap().split(',').next(),
+            Some("101")
+        );
+        for request in requests.iter() {
+            assert_eq!(request.method, "GET");
+            assert_eq!(request.path, "/rest/bug");
+            assert_eq!(request.query("include_fields"), Some("id,status"));
+            assert_eq!(request.query("limit"), Some("500"));
+            assert_eq!(request.query("offset"), Some("0"));
+            assert_eq!(request.header("X-BUGZILLA-API-KEY"), None);
+        }
+    }
+
+    #[test]
+    fn search_follows_pages() {
+        let server = TestServer::start(|request| {
+            let offset: u64 = request.query("offset").unwrap().parse().unwrap();
+            let limit: u64 = request.query("limit").unwrap().parse().unwrap();
+            // Two full pages followed by a partial one
+            let count = if offset < 2 * limit { limit } else { 3 };
+            Response::json(&bugs_json(offset..offset + count))
+        });
+        let client = test_server::client();
+        let bugzilla = bugzilla(&client, &server, Some("secret"));
+
+        let bugs = bugzilla
+            .bugs_by_alias(&["interop-2025-scope".into()], &["id"])
+            .unwrap();
+        assert_eq!(bugs.len(), 2 * PAGE_SIZE + 3);
+        assert_eq!(bugs.last().map(|x| x.id), Some(2 * PAGE_SIZE as u64 + 2));
+
+        let requests = server.requests();
+        let offsets = requests
+            .iter()
+            .map(|x| x.query("offset").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, ["0", "500", "1000"]);
+        for request in requests.iter() {
+            assert_eq!(request.query("alias"), Some("interop-2025-scope"));
+            assert_eq!(request.query("limit"), Some("500"));
+            assert_eq!(request.header("X-BUGZILLA-API-KEY"), Some("secret"));
+        }
+    }
+
+    #[test]
+    fn bugs_in_component_query() {
+        let server = TestServer::start(|_| Response::json(&bugs_json(1..=2)));
+        let client = test_server::client();
+        let bugzilla = bugzilla(&client, &server, None);
+
+        let bugs = bugzilla
+            .bugs_in_component("Testing", "web-platform-tests", true, &["id"])
+            .unwrap();
+        assert_eq!(bugs.iter().map(|x| x.id).collect::<Vec<_>>(), [1, 2]);
+        bugzilla
+            .bugs_in_component("Core", "DOM: Events", false, &[])
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].query("product"), Some("Testing"));
+        assert_eq!(requests[0].query("component"), Some("web-platform-tests"));
+        assert_eq!(requests[0].query("resolution"), Some("---"));
+        assert_eq!(requests[0].query("include_fields"), Some("id"));
+        assert_eq!(requests[0].query("id"), None);
+        assert_eq!(requests[1].query("product"), Some("Core"));
+        assert_eq!(requests[1].query("component"), Some("DOM: Events"));
+        assert_eq!(requests[1].query("resolution"), None);
+        assert_eq!(requests[1].query("include_fields"), None);
+    }
+}
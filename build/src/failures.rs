@@ -6,6 +6,7 @@ use std::fs::File;
 use std::path::Path;
 use time::serde::iso8601;
 use time::OffsetDateTime;
+use wptfyi::bug::{BugTracker, Bugzilla};
 use wptfyi::result::{Run, SearchData, Status};
 use wptfyi::run;
 use wptfyi::search::{AndClause, Clause, LinkClause, NotClause, OrClause, Query, ResultClause};
@@ -37,10 +38,17 @@ fn fx_only_failures_query(untriaged: bool) -> Query {
     }
 
     if untriaged {
+        let link_parts = Bugzilla
+            .link_patterns()
+            .iter()
+            .map(|pattern| {
+                Clause::Link(LinkClause {
+                    link: (*pattern).to_owned(),
+                })
+            })
+            .collect();
         and_parts.push(Clause::Not(NotClause {
-            not: Box::new(Clause::Link(LinkClause {
-                link: "bugzilla.mozilla.org".to_owned(),
-            })),
+            not: Box::new(Clause::Or(OrClause { or: link_parts })),
         }))
     }
 
@@ -0,0 +1,516 @@
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, ETAG, IF_NONE_MATCH, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use time::serde::iso8601;
+use time::OffsetDateTime;
+use url::Url;
+
+/// Number of times to retry a request that hit the rate limit
+const MAX_RETRIES: usize = 3;
+
+/// Longest time to wait for the rate limit to reset before giving up
+const MAX_WAIT_SECS: i64 = 3600;
+
+/// Cached responses that haven't been used for this long are dropped
+const CACHE_MAX_AGE_SECS: i64 = 7 * 24 * 3600;
+
+/// Most cached responses to keep, dropping the least recently used
+const MAX_CACHE_ENTRIES: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    #[serde(default, with = "iso8601::option")]
+    pub merged_at: Option<OffsetDateTime>,
+    pub merge_commit_sha: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Compare {
+    pub commits: Vec<Commit>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Commit {
+    pub sha: String,
+    pub commit: CommitDetail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommitDetail {
+    pub message: String,
+    pub committer: Signature,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Signature {
+    #[serde(with = "iso8601")]
+    pub date: OffsetDateTime,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchResults<T> {
+    #[serde(default)]
+    pub total_count: u64,
+    pub items: Vec<T>,
+}
+
+/// An issue or PR returned by the issue search
+#[derive(Debug, Deserialize)]
+pub struct Issue {
+    pub number: u64,
+    pub body: Option<String>,
+    pub pull_request: Option<IssuePullRequest>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssuePullRequest {
+    #[serde(default, with = "iso8601::option")]
+    pub merged_at: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    remaining: u64,
+    reset: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct CachedResponse {
+    etag: String,
+    body: String,
+    /// Unix time the response was last fetched or reused
+    #[serde(default)]
+    used: i64,
+}
+
+/// Searches have a separate, lower, rate limit
+fn rate_limit_resource(path: &str) -> &'static str {
+    if path.starts_with("search/") {
+        "search"
+    } else {
+        "core"
+    }
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<i64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Client for the GitHub REST API
+pub struct GitHub<'a> {
+    client: &'a reqwest::blocking::Client,
+    base_url: Url,
+    token: Option<String>,
+    rate_limits: RefCell<HashMap<&'static str, RateLimit>>,
+    /// Responses to get_cached requests with an ETag, keyed by URL
+    cache: RefCell<HashMap<String, CachedResponse>>,
+}
+
+impl<'a> GitHub<'a> {
+    pub fn new(
+        client: &'a reqwest::blocking::Client,
+        base_url: Url,
+        token: Option<String>,
+    ) -> GitHub<'a> {
+        GitHub {
+            client,
+            base_url,
+            token,
+            rate_limits: RefCell::new(HashMap::new()),
+            cache: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Create a client for api.github.com.
+    ///
+    /// GITHUB_API_URL overrides the server e.g. to use a local stand-in, and
+    /// GITHUB_TOKEN is used to authenticate if it's set.
+    pub fn from_env(client: &'a reqwest::blocking::Client) -> Result<GitHub<'a>> {
+        let mut base_url =
+            env::var("GITHUB_API_URL").unwrap_or_else(|_| "https://api.github.com/".into());
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+        Ok(GitHub::new(
+            client,
+            Url::parse(&base_url)?,
+            env::var("GITHUB_TOKEN").ok(),
+        ))
+    }
+
+    /// Load cached responses from an earlier run, so unchanged data can be
+    /// fetched with conditional requests
+    pub fn load_cache(&self, path: &Path) -> Result<()> {
+        if let Ok(f) = File::open(path) {
+            *self.cache.borrow_mut() = serde_json::from_reader(f)?;
+        }
+        Ok(())
+    }
+
+    /// Save the cached responses, dropping ones that haven't been used
+    /// recently so the cache doesn't grow without bound
+    pub fn save_cache(&self, path: &Path) -> Result<()> {
+        let min_used = OffsetDateTime::now_utc().unix_timestamp() - CACHE_MAX_AGE_SECS;
+        let mut entries = self
+            .cache
+            .borrow()
+            .iter()
+            .filter(|(_, cached)| cached.used >= min_used)
+            .map(|(url, cached)| (url.clone(), cached.clone()))
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|(_, cached)| -cached.used);
+        entries.truncate(MAX_CACHE_ENTRIES);
+        let out_f = File::create(path)?;
+        serde_json::to_writer(out_f, &entries.into_iter().collect::<HashMap<_, _>>())?;
+        Ok(())
+    }
+
+    /// Sleep until the rate limit resets if there are no requests left
+    fn wait_for_rate_limit(&self, resource: &str) -> Result<()> {
+        let limit = match self.rate_limits.borrow().get(resource) {
+            Some(limit) if limit.remaining == 0 => *limit,
+            _ => return Ok(()),
+        };
+        let wait = limit.reset - OffsetDateTime::now_utc().unix_timestamp() + 1;
+        if wait <= 0 {
+            return Ok(());
+        }
+        if wait > MAX_WAIT_SECS {
+            return Err(anyhow!(
+                "GitHub {} rate limit doesn't reset for {}s",
+                resource,
+                wait
+            ));
+        }
+        info!("Waiting {}s for the GitHub {} rate limit", wait, resource);
+        thread::sleep(Duration::from_secs(wait as u64));
+        Ok(())
+    }
+
+    fn update_rate_limit(&self, resource: &'static str, headers: &HeaderMap) {
+        if let (Some(remaining), Some(reset)) = (
+            header_number(headers, "x-ratelimit-remaining"),
+            header_number(headers, "x-ratelimit-reset"),
+        ) {
+            debug!("GitHub {} rate limit remaining: {}", resource, remaining);
+            self.rate_limits.borrow_mut().insert(
+                resource,
+                RateLimit {
+                    remaining: remaining.max(0) as u64,
+                    reset,
+                },
+            );
+        }
+    }
+
+    fn is_rate_limited(&self, resource: &str, status: StatusCode, headers: &HeaderMap) -> bool {
+        if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+            return false;
+        }
+        headers.contains_key(RETRY_AFTER)
+            || self
+                .rate_limits
+                .borrow()
+                .get(resource)
+                .map(|limit| limit.remaining == 0)
+                .unwrap_or(false)
+    }
+
+    /// GET an API path, waiting for the rate limit to reset when needed
+    pub fn get<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        self.request(path, query, false)
+    }
+
+    /// GET an API path that's requested again on later runs, reusing the
+    /// cached response if the server says it's unchanged
+    pub fn get_cached<T: DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T> {
+        self.request(path, query, true)
+    }
+
+    fn request<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        use_cache: bool,
+    ) -> Result<T> {
+        let mut url = self.base_url.join(path)?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        let resource = rate_limit_resource(path);
+
+        for attempt in 0..=MAX_RETRIES {
+            self.wait_for_rate_limit(resource)?;
+            debug!("GET {}", url);
+            let mut req = self
+                .client
+                .get(url.as_str())
+                .header("Accept", "application/vnd.github+json");
+            if let Some(ref token) = self.token {
+                req = req.bearer_auth(token);
+            }
+            let cached = if use_cache {
+                self.cache.borrow().get(url.as_str()).cloned()
+            } else {
+                None
+            };
+            if let Some(ref cached) = cached {
+                req = req.header(IF_NONE_MATCH, &cached.etag);
+            }
+
+            let resp = req.send()?;
+            let status = resp.status();
+            self.update_rate_limit(resource, resp.headers());
+            if status == StatusCode::NOT_MODIFIED {
+                if let Some(cached) = cached {
+                    debug!("Using cached response for {}", url);
+                    if let Some(entry) = self.cache.borrow_mut().get_mut(url.as_str()) {
+                        entry.used = OffsetDateTime::now_utc().unix_timestamp();
+                    }
+                    return Ok(serde_json::from_str(&cached.body)?);
+                }
+            }
+            if self.is_rate_limited(resource, status, resp.headers()) && attempt < MAX_RETRIES {
+                warn!("Hit the GitHub {} rate limit", resource);
+                if let Some(retry_after) = header_number(resp.headers(), RETRY_AFTER.as_str()) {
+                    thread::sleep(Duration::from_secs(
+                        retry_after.clamp(1, MAX_WAIT_SECS) as u64
+                    ));
+                }
+                continue;
+            }
+
+            let resp = resp.error_for_status()?;
+            let etag = resp
+                .headers()
+                .get(ETAG)
+                .and_then(|x| x.to_str().ok())
+                .map(String::from);
+            let body = resp.text()?;
+            let data = serde_json::from_str(&body)?;
+            if let Some(etag) = etag.filter(|_| use_cache) {
+                self.cache.borrow_mut().insert(
+                    url.to_string(),
+                    CachedResponse {
+                        etag,
+                        body,
+                        used: OffsetDateTime::now_utc().unix_timestamp(),
+                    },
+                );
+            }
+            return Ok(data);
+        }
+        Err(anyhow!("Still rate limited after retrying {}", url))
+    }
+
+    /// PRs associated with a commit
+    pub fn commit_pulls(&self, repo: &str, sha: &str) -> Result<Vec<PullRequest>> {
+        self.get_cached(&format!("repos/{}/commits/{}/pulls", repo, sha), &[])
+    }
+
+    /// One page of the commits after `base` up to `head`.
+    ///
+    /// These responses include every changed file, and each range is only
+    /// fetched once, so they aren't cached.
+    pub fn compare(
+        &self,
+        repo: &str,
+        base: &str,
+        head: &str,
+        per_page: usize,
+        page: usize,
+    ) -> Result<Compare> {
+        self.get(
+            &format!("repos/{}/compare/{}...{}", repo, base, head),
+            &[
+                ("per_page", &per_page.to_string()),
+                ("page", &page.to_string()),
+            ],
+        )
+    }
+
+    /// One page of an issue and PR search
+    pub fn search_issues(
+        &self,
+        query: &str,
+        per_page: usize,
+        page: usize,
+    ) -> Result<SearchResults<Issue>> {
+        self.get_cached(
+            "search/issues",
+            &[
+                ("q", query),
+                ("per_page", &per_page.to_string()),
+                ("page", &page.to_string()),
+            ],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Response, TestServer};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Data {
+        value: u64,
+    }
+
+    fn test_github<'a>(client: &'a reqwest::blocking::Client, server: &TestServer) -> GitHub<'a> {
+        let _env = test_server::lock_env();
+        env::set_var("GITHUB_API_URL", server.url());
+        env::remove_var("GITHUB_TOKEN");
+        let github = GitHub::from_env(client).unwrap();
+        env::remove_var("GITHUB_API_URL");
+        github
+    }
+
+    fn now() -> i64 {
+        OffsetDateTime::now_utc().unix_timestamp()
+    }
+
+    #[test]
+    fn not_modified_reuses_cache() {
+        let server = TestServer::start(|request| {
+            if request.header("If-None-Match") == Some("\"v1\"") {
+                Response::new(304, "")
+            } else {
+                Response::json(r#"{"value": 1}"#).header("ETag", "\"v1\"")
+            }
+        });
+        let client = test_server::client();
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("github-cache.json");
+
+        let github = test_github(&client, &server);
+        let data: Data = github.get_cached("repos/a/b", &[]).unwrap();
+        assert_eq!(data, Data { value: 1 });
+        github.save_cache(&cache_path).unwrap();
+
+        // A later run sends the saved ETag and reuses the saved body
+        let github = test_github(&client, &server);
+        github.load_cache(&cache_path).unwrap();
+        let data: Data = github.get_cached("repos/a/b", &[]).unwrap();
+        assert_eq!(data, Data { value: 1 });
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].header("If-None-Match"), None);
+        assert_eq!(requests[1].header("If-None-Match"), Some("\"v1\""));
+    }
+
+    #[test]
+    fn uncached_requests() {
+        let server =
+            TestServer::start(|_| Response::json(r#"{"value": 1}"#).header("ETag", "\"v1\""));
+        let client = test_server::client();
+        let github = test_github(&client, &server);
+
+        for _ in 0..2 {
+            let data: Data = github.get("repos/a/compare/b...c", &[]).unwrap();
+            assert_eq!(data, Data { value: 1 });
+        }
+        assert!(github.cache.borrow().is_empty());
+        let requests = server.requests();
+        assert!(requests.iter().all(|x| x.header("If-None-Match").is_none()));
+    }
+
+    #[test]
+    fn save_cache_prunes_entries() {
+        let server = TestServer::start(|_| Response::new(500, ""));
+        let client = test_server::client();
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("github-cache.json");
+
+        let github = test_github(&client, &server);
+        let entry = |used| CachedResponse {
+            etag: "etag".into(),
+            body: "{}".into(),
+            used,
+        };
+        {
+            let mut cache = github.cache.borrow_mut();
+            cache.insert("stale".into(), entry(now() - CACHE_MAX_AGE_SECS - 60));
+            for i in 0..MAX_CACHE_ENTRIES + 1 {
+                cache.insert(format!("url{}", i), entry(now() - i as i64));
+            }
+        }
+        github.save_cache(&cache_path).unwrap();
+        github.load_cache(&cache_path).unwrap();
+
+        let cache = github.cache.borrow();
+        assert_eq!(cache.len(), MAX_CACHE_ENTRIES);
+        assert!(!cache.contains_key("stale"));
+        assert!(cache.contains_key("url0"));
+        assert!(!cache.contains_key(&format!("url{}", MAX_CACHE_ENTRIES)));
+    }
+
+    #[test]
+    fn waits_for_rate_limit_reset() {
+        let count = AtomicUsize::new(0);
+        let server = TestServer::start(move |_| {
+            let reset = (now() + 1).to_string();
+            if count.fetch_add(1, Ordering::SeqCst) == 0 {
+                Response::new(403, "")
+                    .header("x-ratelimit-remaining", "0")
+                    .header("x-ratelimit-reset", &reset)
+            } else {
+                Response::json(r#"{"value": 2}"#)
+                    .header("x-ratelimit-remaining", "29")
+                    .header("x-ratelimit-reset", &reset)
+            }
+        });
+        let client = test_server::client();
+        let github = test_github(&client, &server);
+
+        let start = Instant::now();
+        let data: Data = github.get("search/issues", &[("q", "is:pr")]).unwrap();
+        assert_eq!(data, Data { value: 2 });
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn retries_after_delay() {
+        let count = AtomicUsize::new(0);
+        let server = TestServer::start(move |_| {
+            if count.fetch_add(1, Ordering::SeqCst) == 0 {
+                Response::new(429, "").header("Retry-After", "1")
+            } else {
+                Response::json(r#"{"value": 3}"#)
+            }
+        });
+        let client = test_server::client();
+        let github = test_github(&client, &server);
+
+        let start = Instant::now();
+        let data: Data = github.get("repos/a/b", &[]).unwrap();
+        assert_eq!(data, Data { value: 3 });
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_retries() {
+        let server = TestServer::start(|_| Response::new(429, "").header("Retry-After", "1"));
+        let client = test_server::client();
+        let github = test_github(&client, &server);
+
+        assert!(github.get::<Data>("repos/a/b", &[]).is_err());
+        assert_eq!(server.requests().len(), MAX_RETRIES + 1);
+    }
+}
@@ -1,36 +1,117 @@
-use crate::network::{self, get, post};
+use crate::bugzilla::{Bug, Bugzilla};
+use crate::network::{get, post};
+use crate::wpt_data::WptData;
 use anyhow::{anyhow, Result};
-use log::{debug, info};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use std::env;
 use std::fs::{self, File};
 use std::path::Path;
-use url::Url;
-use wptfyi::interop::{Category, FocusArea};
-use wptfyi::metadata::MetadataEntry;
-use wptfyi::result::Status;
-use wptfyi::search::{AndClause, Clause, LabelClause, NotClause, OrClause, Query, ResultClause};
+use time::format_description::well_known::Iso8601;
+use wptfyi::bug::{BugLinkParser, BugRef};
+use wptfyi::interop::{BrowserChannel, Category};
+use wptfyi::metadata::{MetadataEntry, MetadataResult};
+use wptfyi::result::{LegacyStatus, Status, TestResults};
+use wptfyi::search::{
+    AndClause, Clause, LabelClause, NotClause, OrClause, PatternClause, Query, ResultClause,
+};
 use wptfyi::{interop, metadata, result, run, search, Wptfyi};
 
+#[derive(Debug)]
 struct InteropYear {
     year: u64,
-    end_date: time::Date,
+    end_date: Option<time::Date>,
+    active: bool,
+    /// Focus area names for meta bugs whose alias isn't the focus area name
+    meta_bug_focus_areas: BTreeMap<String, String>,
 }
 
-fn interop_years() -> Vec<InteropYear> {
-    vec![
-        InteropYear {
-            year: 2025,
-            end_date: time::Date::from_calendar_date(2026, time::Month::February, 12).unwrap(),
-        },
-        InteropYear {
-            year: 2026,
-            end_date: time::Date::from_calendar_date(2027, time::Month::February, 11).unwrap(),
-        },
-    ]
+impl InteropYear {
+    /// The focus area tracked by a meta bug
+    fn meta_bug_focus_area<'a>(&'a self, alias: &'a str) -> &'a str {
+        self.meta_bug_focus_areas
+            .get(alias)
+            .map(|x| x.as_str())
+            .unwrap_or(alias)
+    }
+}
+
+/// Per-year overrides read from interop_years.json
+#[derive(Debug, Default, Deserialize)]
+struct InteropYearConfig {
+    #[serde(default)]
+    end_date: Option<String>,
+    #[serde(default)]
+    active: Option<bool>,
+    /// Meta bug alias to focus area name, for aliases that differ from the
+    /// focus area name
+    #[serde(default)]
+    meta_bug_focus_areas: BTreeMap<String, String>,
+}
+
+fn load_year_config(path: &Path) -> Result<BTreeMap<String, InteropYearConfig>> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Get the interop years listed in the wpt.fyi metadata.
+///
+/// A year is active until its configured end date. Years without an end
+/// date are active only while they are the most recent year.
+fn interop_years(
+    interop_metadata: &interop::InteropMetadata,
+    config: &BTreeMap<String, InteropYearConfig>,
+    today: time::Date,
+) -> Result<Vec<InteropYear>> {
+    let years = interop_metadata.interop_years();
+    let latest_year = years.iter().max().copied();
+    let mut rv = Vec::with_capacity(years.len());
+    for year in years {
+        let year_config = config.get(&year.to_string());
+        let end_date = year_config
+            .and_then(|x| x.end_date.as_ref())
+            .map(|x| time::Date::parse(x, &Iso8601::DATE))
+            .transpose()?;
+        let active = year_config
+            .and_then(|x| x.active)
+            .unwrap_or(match end_date {
+                Some(end_date) => end_date >= today,
+                None => Some(year) == latest_year,
+            });
+        rv.push(InteropYear {
+            year,
+            end_date,
+            active,
+            meta_bug_focus_areas: year_config
+                .map(|x| x.meta_bug_focus_areas.clone())
+                .unwrap_or_default(),
+        })
+    }
+    Ok(rv)
 }
 
-fn fx_failures_query(labels: &[&str]) -> Query {
+/// Years named in AREWEWPTYET_INTEROP_YEARS, which are processed even if
+/// they are no longer active.
+fn requested_years() -> Result<BTreeSet<u64>> {
+    match env::var("AREWEWPTYET_INTEROP_YEARS") {
+        Ok(years) => years
+            .split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| {
+                x.parse::<u64>()
+                    .map_err(|_| anyhow!("Invalid interop year {}", x))
+            })
+            .collect(),
+        Err(_) => Ok(BTreeSet::new()),
+    }
+}
+
+fn fx_failures_query(firefox: &str, tests: &FocusAreaTests) -> Query {
     let pass_statuses = &[Status::Ok, Status::Pass];
 
     let mut root_clause = AndClause {
@@ -40,45 +121,174 @@ fn fx_failures_query(labels: &[&str]) -> Query {
     for status in pass_statuses.iter() {
         root_clause.push(Clause::Not(NotClause {
             not: Box::new(Clause::Result(ResultClause {
-                browser_name: "firefox".to_owned(),
+                browser_name: firefox.to_owned(),
                 status: status.clone(),
             })),
         }));
     }
 
-    if !labels.is_empty() {
-        let mut labels_clause = OrClause {
-            or: Vec::with_capacity(labels.len()),
+    if !tests.is_empty() {
+        root_clause.push(tests.clause());
+    }
+
+    Query {
+        query: Clause::And(root_clause),
+    }
+}
+
+/// The tests making up a focus area.
+///
+/// Official focus areas are defined by their labels, team focus areas can
+/// also list test path globs and individual tests.
+#[derive(Debug, Default)]
+pub struct FocusAreaTests<'a> {
+    pub labels: Vec<&'a str>,
+    /// Globs where `*` matches within a single path segment and `**`
+    /// matches across segments
+    pub paths: Vec<&'a str>,
+    pub tests: Vec<&'a str>,
+}
+
+impl<'a> FocusAreaTests<'a> {
+    pub fn from_labels(labels: Vec<&'a str>) -> FocusAreaTests<'a> {
+        FocusAreaTests {
+            labels,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.paths.is_empty() && self.tests.is_empty()
+    }
+
+    /// Check that every glob starts with a literal directory, see `glob_prefix`
+    pub fn check_paths(&self) -> Result<()> {
+        match self.paths.iter().find(|path| glob_prefix(path).is_none()) {
+            Some(path) => Err(anyhow!(
+                "Path glob {} must start with a directory e.g. /css/css-grid/",
+                path
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// wpt.fyi search clause for the tests.
+    ///
+    /// Globs are searched for by their literal prefix, so the results can
+    /// include extra tests that are removed by `retain_matching`.
+    fn clause(&self) -> Clause {
+        let mut clause = OrClause {
+            or: Vec::with_capacity(self.labels.len() + self.paths.len() + self.tests.len()),
         };
-        for label in labels {
-            labels_clause.push(Clause::Label(LabelClause {
+        for label in self.labels.iter() {
+            clause.push(Clause::Label(LabelClause {
                 label: (*label).into(),
             }));
         }
-        root_clause.push(Clause::Or(labels_clause));
+        for prefix in self.paths.iter().filter_map(|path| glob_prefix(path)) {
+            clause.push(Clause::Pattern(PatternClause {
+                pattern: prefix.into(),
+            }));
+        }
+        for test in self.tests.iter() {
+            clause.push(Clause::Pattern(PatternClause {
+                pattern: (*test).into(),
+            }));
+        }
+        Clause::Or(clause)
     }
 
-    Query {
-        query: Clause::And(root_clause),
+    fn matches(&self, test: &str, test_labels: Option<&BTreeSet<&str>>) -> bool {
+        self.tests.contains(&test)
+            || self.paths.iter().any(|path| glob_match(path, test))
+            || test_labels
+                .map(|labels| self.labels.iter().any(|label| labels.contains(label)))
+                .unwrap_or(false)
+    }
+
+    /// Remove search results for tests that aren't in the focus area
+    pub fn retain_matching(
+        &self,
+        data: &mut result::SearchData,
+        metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+    ) {
+        if self.paths.is_empty() && self.tests.is_empty() {
+            return;
+        }
+        let labels = metadata::test_labels(metadata);
+        data.results
+            .retain(|result| self.matches(&result.test, labels.get(result.test.as_str())));
+    }
+}
+
+/// The literal part of a glob before its first wildcard.
+///
+/// This is what gets searched for, so it must include at least one
+/// directory; otherwise the search would cover the whole of wpt.
+fn glob_prefix(glob: &str) -> Option<&str> {
+    let prefix = glob.split('*').next().unwrap_or_default();
+    let dir = &prefix[..prefix.rfind('/')? + 1];
+    if dir.starts_with('/') && dir.len() > 1 {
+        Some(prefix)
+    } else {
+        None
+    }
+}
+
+fn glob_match(pattern: &str, path: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        path.char_indices()
+            .map(|(idx, _)| idx)
+            .chain([path.len()])
+            .any(|idx| glob_match(rest, &path[idx..]))
+    } else if let Some(rest) = pattern.strip_prefix('*') {
+        let segment_end = path.find('/').unwrap_or(path.len());
+        path[..segment_end]
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain([segment_end])
+            .any(|idx| glob_match(rest, &path[idx..]))
+    } else {
+        match (pattern.chars().next(), path.chars().next()) {
+            (None, None) => true,
+            (Some(expected), Some(actual)) if expected == actual => {
+                glob_match(&pattern[expected.len_utf8()..], &path[actual.len_utf8()..])
+            }
+            _ => false,
+        }
     }
 }
 
-fn get_run_data(wptfyi: &Wptfyi, client: &reqwest::blocking::Client) -> Result<Vec<result::Run>> {
+fn get_run_data(
+    wptfyi: &Wptfyi,
+    client: &reqwest::blocking::Client,
+    channel: BrowserChannel,
+) -> Result<Vec<result::Run>> {
     let mut runs = wptfyi.runs();
-    for product in ["chrome", "firefox", "safari"].iter() {
-        runs.add_product(product, "experimental")
+    for product in channel.browsers().iter() {
+        runs.add_product(product, channel.run_channel())
     }
     runs.add_label("master");
     runs.set_max_count(100);
     Ok(run::parse(&get(client, &String::from(runs.url()), None)?)?)
 }
 
-fn get_metadata(
+/// Products to fetch metadata for, read from metadata_products.json
+pub fn metadata_products(path: &Path) -> Result<Vec<String>> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(vec!["firefox".into()])
+    }
+}
+
+pub fn get_metadata(
     wptfyi: &Wptfyi,
     client: &reqwest::blocking::Client,
+    products: &[String],
 ) -> Result<BTreeMap<String, Vec<MetadataEntry>>> {
     let mut metadata = wptfyi.metadata();
-    for product in ["firefox"].iter() {
+    for product in products.iter() {
         metadata.add_product(product)
     }
     Ok(metadata::parse(&get(
@@ -91,14 +301,42 @@ fn get_metadata(
 pub fn get_fx_failures(
     wptfyi: &Wptfyi,
     client: &reqwest::blocking::Client,
+    channel: BrowserChannel,
+    run_ids: &[i64],
+    tests: &FocusAreaTests,
+) -> Result<result::SearchData> {
+    let mut search = wptfyi.search();
+    for product in channel.browsers().iter() {
+        search.add_product(product, channel.run_channel())
+    }
+    search.set_query(run_ids, fx_failures_query(channel.browsers()[0], tests));
+    search.add_label("master");
+    Ok(search::parse(&post(
+        client,
+        &String::from(search.url()),
+        None,
+        search.body(),
+    )?)?)
+}
+
+/// Get all the tests in a focus area, whatever their status
+pub fn get_focus_area_tests(
+    wptfyi: &Wptfyi,
+    client: &reqwest::blocking::Client,
+    channel: BrowserChannel,
     run_ids: &[i64],
-    labels: &[&str],
+    tests: &FocusAreaTests,
 ) -> Result<result::SearchData> {
     let mut search = wptfyi.search();
-    for product in ["chrome", "firefox", "safari"].iter() {
-        search.add_product(product, "experimental")
+    for product in channel.browsers().iter() {
+        search.add_product(product, channel.run_channel())
     }
-    search.set_query(run_ids, fx_failures_query(labels));
+    search.set_query(
+        run_ids,
+        Query {
+            query: tests.clause(),
+        },
+    );
     search.add_label("master");
     Ok(search::parse(&post(
         client,
@@ -111,9 +349,9 @@ pub fn get_fx_failures(
 pub fn get_interop_data(
     wptfyi: &Wptfyi,
     client: &reqwest::blocking::Client,
-) -> Result<BTreeMap<String, interop::YearData>> {
+) -> Result<interop::InteropMetadata> {
     let runs = wptfyi.interop_data();
-    Ok(interop::parse(&get(
+    Ok(interop::parse_metadata(&get(
         client,
         &String::from(runs.url()),
         None,
@@ -135,7 +373,7 @@ pub fn get_interop_scores(
     wptfyi: &Wptfyi,
     client: &reqwest::blocking::Client,
     year: u64,
-    browser_channel: interop::BrowserChannel,
+    browser_channel: BrowserChannel,
 ) -> Result<Vec<interop::ScoreRow>> {
     Ok(interop::parse_scores(&get(
         client,
@@ -144,11 +382,11 @@ pub fn get_interop_scores(
     )?)?)
 }
 
-fn latest_runs(runs: &[result::Run]) -> Result<Vec<&result::Run>> {
+fn latest_runs(runs: &[result::Run], browser_count: usize) -> Result<Vec<&result::Run>> {
     let mut runs_by_commit = run::runs_by_commit(runs);
     let latest_rev = runs_by_commit
         .iter()
-        .filter(|(_, value)| value.len() == 3)
+        .filter(|(_, value)| value.len() == browser_count)
         .max_by(|(_, value_1), (_, value_2)| {
             let date_1 = value_1.iter().map(|x| x.created_at).max();
             let date_2 = value_2.iter().map(|x| x.created_at).max();
@@ -160,24 +398,444 @@ fn latest_runs(runs: &[result::Run]) -> Result<Vec<&result::Run>> {
         .ok_or_else(|| anyhow!("Failed to find any complete runs"))
 }
 
-pub fn write_focus_area(
+/// Get the latest complete set of runs for a channel, in the channel's
+/// browser order.
+pub fn get_channel_runs(
     fyi: &Wptfyi,
     client: &reqwest::blocking::Client,
-    year: u64,
+    channel: BrowserChannel,
+) -> Result<Vec<result::Run>> {
+    let runs = get_run_data(fyi, client, channel)?;
+    let latest = latest_runs(&runs, channel.browsers().len())?;
+    channel
+        .browsers()
+        .iter()
+        .map(|browser| {
+            latest
+                .iter()
+                .find(|run| run.browser_name == *browser)
+                .map(|run| (*run).clone())
+                .ok_or_else(|| anyhow!("No {} run found", browser))
+        })
+        .collect()
+}
+
+/// Directory holding the output for a given year and channel.
+///
+/// Experimental desktop data lives at the top level of the year
+/// directory, other channels get their own subdirectory.
+fn interop_dir(year: u64, channel: BrowserChannel) -> String {
+    match channel {
+        BrowserChannel::Experimental => format!("../docs/interop-{}", year),
+        _ => format!("../docs/interop-{}/{}", year, channel),
+    }
+}
+
+/// Data shared by all the focus areas written to a single directory
+pub struct InteropContext<'a> {
+    pub fyi: &'a Wptfyi,
+    pub client: &'a reqwest::blocking::Client,
+    pub channel: BrowserChannel,
+    /// Output directory for the focus area data
+    pub dir: String,
+    pub runs: &'a [result::Run],
+    pub metadata: &'a BTreeMap<String, Vec<MetadataEntry>>,
+    pub bug_links: &'a BugLinkParser,
+    /// Fetch each test's full results, for subtest detail and scoped bug links
+    pub subtest_detail: bool,
+    /// Number of focus areas counting toward the overall score
+    pub focus_area_count: usize,
+}
+
+impl InteropContext<'_> {
+    fn run_ids(&self) -> Vec<i64> {
+        self.runs.iter().map(|x| x.id).collect()
+    }
+}
+
+/// Full results for a single test in each browser, in the channel's
+/// browser order
+pub type TestDetail = Vec<Option<TestResults>>;
+
+fn get_test_results(
+    client: &reqwest::blocking::Client,
+    run: &result::Run,
+    test: &str,
+) -> Option<TestResults> {
+    let url = run.test_results_url(test)?;
+    let data = match get(client, url.as_str(), None) {
+        Ok(data) => data,
+        Err(err) => {
+            warn!(
+                "Failed to get {} results for {}: {}",
+                run.browser_name, test, err
+            );
+            return None;
+        }
+    };
+    match result::parse_test_results(&data) {
+        Ok(results) => Some(results),
+        Err(err) => {
+            warn!(
+                "Failed to parse {} results for {}: {}",
+                run.browser_name, test, err
+            );
+            None
+        }
+    }
+}
+
+fn get_test_detail(ctx: &InteropContext, test: &str) -> TestDetail {
+    ctx.runs
+        .iter()
+        .map(|run| get_test_results(ctx.client, run, test))
+        .collect()
+}
+
+fn status_column(results: Option<&TestResults>, subtest: Option<&str>) -> String {
+    let status = match (results, subtest) {
+        (Some(results), Some(subtest)) => results.subtest(subtest).map(|x| &x.status),
+        (Some(results), None) => Some(&results.status),
+        (None, _) => None,
+    };
+    status.map(|x| x.to_string()).unwrap_or_default()
+}
+
+/// The failing parts of a test covered by a bug
+#[derive(Debug)]
+pub enum BugCoverage {
+    Test,
+    Subtests(BTreeSet<String>),
+}
+
+impl BugCoverage {
+    fn covers(&self, subtest: Option<&str>) -> bool {
+        match (self, subtest) {
+            (BugCoverage::Test, _) => true,
+            (BugCoverage::Subtests(subtests), Some(subtest)) => subtests.contains(subtest),
+            (BugCoverage::Subtests(_), None) => false,
+        }
+    }
+
+    fn add_subtest(&mut self, subtest: &str) {
+        if let BugCoverage::Subtests(subtests) = self {
+            subtests.insert(subtest.to_owned());
+        }
+    }
+}
+
+/// Firefox bugs covering a failing test
+#[derive(Debug, Default)]
+pub struct TestTriage {
+    pub bugs: BTreeMap<BugRef, BugCoverage>,
+    /// Number of failing subtests not covered by any bug
+    pub untriaged: i64,
+}
+
+impl TestTriage {
+    fn bugs_for(&self, subtest: Option<&str>) -> Vec<String> {
+        self.bugs
+            .iter()
+            .filter(|(_, coverage)| coverage.covers(subtest))
+            .map(|(bug, _)| bug.to_string())
+            .collect()
+    }
+}
+
+/// Firefox bug links in the metadata for a test, along with the entry
+/// that scopes each link to subtests and statuses
+fn fx_bug_links<'a>(ctx: &'a InteropContext, test: &str) -> Vec<(BugRef, &'a MetadataEntry)> {
+    ctx.metadata
+        .get(test)
+        .map(|test_meta| {
+            test_meta
+                .iter()
+                .filter(|entry| entry.product == "firefox")
+                .filter_map(|entry| entry.bug(ctx.bug_links).map(|bug| (bug, entry)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn status_matches(result: &MetadataResult, status: Option<&Status>) -> bool {
+    match (result.status(), status) {
+        (Some(expected), Some(status)) => expected == *status,
+        _ => true,
+    }
+}
+
+/// Work out which failing parts of a test each bug covers.
+///
+/// Links without results cover the whole test. Links scoped to a subtest
+/// only count if that subtest currently fails with the linked status.
+/// Without Firefox results subtest scoped links can't be checked, so
+/// they are kept but nothing counts as untriaged.
+fn test_triage(
+    links: &[(BugRef, &MetadataEntry)],
+    fx_results: Option<&TestResults>,
+    failures: i64,
+) -> TestTriage {
+    let mut triage = TestTriage::default();
+    for (bug, entry) in links.iter() {
+        if !entry.is_scoped() {
+            triage.bugs.insert(bug.clone(), BugCoverage::Test);
+            continue;
+        }
+        for result in entry.results.iter() {
+            match result.subtest.as_deref() {
+                None => {
+                    if status_matches(result, fx_results.map(|x| &x.status)) {
+                        triage.bugs.insert(bug.clone(), BugCoverage::Test);
+                    }
+                }
+                Some(subtest) => {
+                    let subtest_status = match fx_results {
+                        Some(fx_results) => match fx_results.subtest(subtest) {
+                            Some(subtest_result) if !subtest_result.status.is_pass() => {
+                                Some(&subtest_result.status)
+                            }
+                            _ => continue,
+                        },
+                        None => None,
+                    };
+                    if status_matches(result, subtest_status) {
+                        triage
+                            .bugs
+                            .entry(bug.clone())
+                            .or_insert_with(|| BugCoverage::Subtests(BTreeSet::new()))
+                            .add_subtest(subtest);
+                    }
+                }
+            }
+        }
+    }
+
+    triage.untriaged = if triage.bugs.is_empty() {
+        failures
+    } else if triage.bugs_for(None).is_empty() {
+        match fx_results {
+            Some(fx_results) => fx_results
+                .subtests
+                .iter()
+                .filter(|x| !x.status.is_pass() && triage.bugs_for(Some(&x.name)).is_empty())
+                .count() as i64,
+            None => 0,
+        }
+    } else {
+        0
+    };
+    triage
+}
+
+/// Write the tests and subtests covered by each bug in a focus area
+fn write_focus_area_bugs(
+    ctx: &InteropContext,
     name: &str,
-    focus_area: &FocusArea,
-    run_ids: &[i64],
-    categories_by_name: &BTreeMap<String, &Category>,
-    metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+    triage: &BTreeMap<String, TestTriage>,
 ) -> Result<()> {
-    if !focus_area.counts_toward_score {
-        return Ok(());
+    let path = format!("{}/bugs/{}.csv", ctx.dir, name);
+    let data_path = Path::new(&path);
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
     }
-    let labels = &categories_by_name
+    let out_f = File::create(data_path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+
+    let mut by_bug: BTreeMap<&BugRef, Vec<(&str, &BugCoverage)>> = BTreeMap::new();
+    for (test, test_triage) in triage.iter() {
+        for (bug, coverage) in test_triage.bugs.iter() {
+            by_bug.entry(bug).or_default().push((test, coverage));
+        }
+    }
+
+    writer.write_record(["Bug", "Test", "Subtests"])?;
+    for (bug, tests) in by_bug.iter() {
+        for (test, coverage) in tests.iter() {
+            let subtests = match coverage {
+                BugCoverage::Test => String::new(),
+                BugCoverage::Subtests(subtests) => subtests
+                    .iter()
+                    .map(|x| x.as_ref())
+                    .collect::<Vec<&str>>()
+                    .join(";"),
+            };
+            writer.write_record([&bug.to_string(), *test, &subtests])?;
+        }
+    }
+    Ok(())
+}
+
+/// Write each non-passing Firefox subtest in a focus area along with the
+/// status in each browser and the Firefox failure message.
+fn write_subtest_detail(
+    ctx: &InteropContext,
+    name: &str,
+    details: &BTreeMap<String, TestDetail>,
+    impacts: &BTreeMap<String, ScoreImpact>,
+    triage: &BTreeMap<String, TestTriage>,
+) -> Result<()> {
+    let path = format!("{}/subtests/{}.csv", ctx.dir, name);
+    let data_path = Path::new(&path);
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
+    }
+    let out_f = File::create(data_path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+
+    writer.write_record([
+        "Test",
+        "Subtest",
+        "Firefox Status",
+        "Chrome Status",
+        "Safari Status",
+        "Firefox Message",
+        "Focus Area Gain",
+        "Bugs",
+    ])?;
+    for (test, detail) in details.iter() {
+        let impact = impacts.get(test).copied().unwrap_or_default();
+        let test_triage = triage.get(test);
+        let fx_results = match detail.first() {
+            Some(Some(results)) => results,
+            _ => continue,
+        };
+        let mut rows = Vec::new();
+        if !fx_results.status.is_pass() {
+            rows.push((None, fx_results.message.as_deref()));
+        }
+        for subtest in fx_results.subtests.iter() {
+            if !subtest.status.is_pass() {
+                rows.push((Some(subtest.name.as_str()), subtest.message.as_deref()));
+            }
+        }
+        for (subtest, message) in rows {
+            let mut record = Vec::with_capacity(8);
+            record.push(test.clone());
+            record.push(subtest.unwrap_or_default().to_owned());
+            for browser_results in detail.iter() {
+                record.push(status_column(browser_results.as_ref(), subtest));
+            }
+            record.push(message.unwrap_or_default().to_owned());
+            // A failing harness status doesn't count toward the score
+            record.push(if subtest.is_some() || fx_results.subtests.is_empty() {
+                format!("{:.4}", impact.per_subtest)
+            } else {
+                String::new()
+            });
+            record.push(
+                test_triage
+                    .map(|x| x.bugs_for(subtest).join(" "))
+                    .unwrap_or_default(),
+            );
+            writer.write_record(&record)?;
+        }
+    }
+    Ok(())
+}
+
+/// Gain in Firefox's score, in percentage points, from fixing a test
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScoreImpact {
+    /// Gain in the focus area score if the whole test passed
+    pub focus_area: f64,
+    /// Gain in the overall interop score if the whole test passed
+    pub interop: f64,
+    /// Gain in the focus area score for each additional passing subtest
+    pub per_subtest: f64,
+}
+
+impl ScoreImpact {
+    /// Each test contributes equally to its focus area's score, with the
+    /// test score being the fraction of its subtests that pass. The
+    /// overall score is the mean of the focus area scores.
+    fn new(status: &LegacyStatus, test_count: usize, focus_area_count: usize) -> ScoreImpact {
+        if status.total == 0 || test_count == 0 {
+            return ScoreImpact::default();
+        }
+        let per_subtest = 100. / (status.total as f64 * test_count as f64);
+        let focus_area = per_subtest * (status.total - status.passes) as f64;
+        // Team focus areas don't count toward the interop score
+        let interop = if focus_area_count == 0 {
+            0.
+        } else {
+            focus_area / focus_area_count as f64
+        };
+        ScoreImpact {
+            focus_area,
+            interop,
+            per_subtest,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TestFailure {
+    /// Number of failing Firefox subtests
+    pub failures: i64,
+    pub impact: ScoreImpact,
+}
+
+pub type TestFailures = BTreeMap<String, TestFailure>;
+
+fn focus_area_labels<'a>(
+    categories_by_name: &'a BTreeMap<String, &'a Category>,
+    name: &str,
+) -> Result<Vec<&'a str>> {
+    Ok(categories_by_name
         .get(name)
         .ok_or_else(|| anyhow!("Didn't find category {}", name))?
-        .labels;
-    let path = format!("../docs/interop-{}/{}.csv", year, name);
+        .labels
+        .iter()
+        .filter_map(|x| {
+            if x.starts_with("interop-") {
+                Some(x.as_ref())
+            } else {
+                None
+            }
+        })
+        .collect())
+}
+
+/// Work out the score impact of fixing each test in a focus area from
+/// the Firefox results for every test in the area.
+fn focus_area_impact(
+    ctx: &InteropContext,
+    tests: &FocusAreaTests,
+) -> Result<BTreeMap<String, ScoreImpact>> {
+    let mut all_tests =
+        get_focus_area_tests(ctx.fyi, ctx.client, ctx.channel, &ctx.run_ids(), tests)?;
+    tests.retain_matching(&mut all_tests, ctx.metadata);
+    let fx_idx = all_tests
+        .runs
+        .iter()
+        .position(|x| x.browser_name == ctx.channel.browsers()[0])
+        .ok_or_else(|| anyhow!("Didn't get Firefox results"))?;
+    let test_count = all_tests.results.len();
+    Ok(all_tests
+        .results
+        .iter()
+        .filter_map(|result| {
+            result.legacy_status.get(fx_idx).map(|status| {
+                (
+                    result.test.clone(),
+                    ScoreImpact::new(status, test_count, ctx.focus_area_count),
+                )
+            })
+        })
+        .collect())
+}
+
+pub fn write_focus_area(
+    ctx: &InteropContext,
+    name: &str,
+    tests: &FocusAreaTests,
+) -> Result<TestFailures> {
+    let mut fx_failures = TestFailures::new();
+    let path = format!("{}/{}.csv", ctx.dir, name);
     let data_path = Path::new(&path);
     if let Some(dir_name) = data_path.parent() {
         fs::create_dir_all(dir_name)?;
@@ -187,22 +845,10 @@ pub fn write_focus_area(
         .quote_style(csv::QuoteStyle::NonNumeric)
         .from_writer(out_f);
 
-    let results = get_fx_failures(
-        fyi,
-        client,
-        run_ids,
-        &labels
-            .iter()
-            .filter_map(|x| {
-                if x.starts_with("interop-") {
-                    Some(x.as_ref())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<&str>>(),
-    )?;
-    let order = &["firefox", "chrome", "safari"];
+    let mut results = get_fx_failures(ctx.fyi, ctx.client, ctx.channel, &ctx.run_ids(), tests)?;
+    tests.retain_matching(&mut results, ctx.metadata);
+    let impacts = focus_area_impact(ctx, tests)?;
+    let order = ctx.channel.browsers();
     let maybe_browser_list = results
         .runs
         .iter()
@@ -219,47 +865,118 @@ pub fn write_focus_area(
         "Chrome Failures",
         "Safari Failures",
         "Bugs",
+        "Focus Area Gain",
+        "Interop Gain",
+        "Gain Per Subtest",
+        "Untriaged Failures",
     ])?;
+
+    let details: BTreeMap<String, TestDetail> = if ctx.subtest_detail {
+        results
+            .results
+            .iter()
+            .map(|result| (result.test.clone(), get_test_detail(ctx, &result.test)))
+            .collect()
+    } else {
+        BTreeMap::new()
+    };
+    let mut triage = BTreeMap::new();
+
     for result in results.results.iter() {
+        let impact = impacts.get(&result.test).copied().unwrap_or_default();
         let mut scores = [String::new(), String::new(), String::new()];
-        for (output_idx, browser_idx) in browser_list.iter().enumerate() {
-            if let Some(status) = result.legacy_status.get(*browser_idx) {
-                if output_idx == 0 {
+        for (run_idx, browser_idx) in browser_list.iter().enumerate() {
+            if let Some(status) = result.legacy_status.get(run_idx) {
+                if *browser_idx == 0 {
                     // For Firefox output the total as this is the number of failures
-                    scores[output_idx].push_str(&format!("{}", status.total));
+                    scores[*browser_idx].push_str(&format!("{}", status.total));
+                    fx_failures.insert(
+                        result.test.clone(),
+                        TestFailure {
+                            failures: status.total,
+                            impact,
+                        },
+                    );
                 } else {
-                    // For Firefox output the total as this is the number of failures
-                    scores[output_idx].push_str(&format!("{}", status.total - status.passes));
+                    scores[*browser_idx].push_str(&format!("{}", status.total - status.passes));
                 }
             }
         }
-        let mut bugs = BTreeSet::new();
-        if let Some(test_meta) = metadata.get(&result.test) {
-            for metadata_entry in test_meta.iter() {
-                if metadata_entry.product != "firefox"
-                    || !metadata_entry
-                        .url
-                        .starts_with("https://bugzilla.mozilla.org")
-                {
-                    continue;
-                }
-                // For now add all bugs irrespective of status or subtest
-                if let Ok(bug_url) = Url::parse(&metadata_entry.url) {
-                    if let Some((_, bug_id)) = bug_url.query_pairs().find(|(key, _)| key == "id") {
-                        bugs.insert(bug_id.into_owned());
-                    }
-                }
-            }
-        }
-        let mut bugs_col = String::with_capacity(8 * bugs.len());
-        for bug in bugs.iter() {
+        let links = fx_bug_links(ctx, &result.test);
+        // Per-test results are only fetched with subtest detail, since that's a
+        // request per test. Without them scoped links are assumed to match.
+        let fx_results = details
+            .get(&result.test)
+            .and_then(|detail| detail.first())
+            .and_then(|x| x.as_ref());
+        let fx_failure_count = fx_failures
+            .get(&result.test)
+            .map(|x| x.failures)
+            .unwrap_or(0);
+        let test_triage = test_triage(&links, fx_results, fx_failure_count);
+        let mut bugs_col = String::with_capacity(8 * test_triage.bugs.len());
+        for bug in test_triage.bugs.keys() {
             if !bugs_col.is_empty() {
                 bugs_col.push(' ');
             }
-            bugs_col.push_str(bug);
+            bugs_col.push_str(&bug.to_string());
         }
-        let record = &[&result.test, &scores[0], &scores[1], &scores[2], &bugs_col];
+        let record = &[
+            &result.test,
+            &scores[0],
+            &scores[1],
+            &scores[2],
+            &bugs_col,
+            &format!("{:.4}", impact.focus_area),
+            &format!("{:.4}", impact.interop),
+            &format!("{:.4}", impact.per_subtest),
+            &test_triage.untriaged.to_string(),
+        ];
         writer.write_record(record)?;
+        triage.insert(result.test.clone(), test_triage);
+    }
+
+    write_focus_area_bugs(ctx, name, &triage)?;
+    if ctx.subtest_detail {
+        write_subtest_detail(ctx, name, &details, &impacts, &triage)?;
+    }
+    Ok(fx_failures)
+}
+
+/// Write the tests in a focus area that have fewer Firefox failures on
+/// the experimental channel than on the stable one.
+///
+/// These are the fixes that will reach users in the next release.
+pub fn write_next_release_fixes(
+    stable_dir: &str,
+    name: &str,
+    stable_failures: &TestFailures,
+    experimental_failures: &TestFailures,
+) -> Result<()> {
+    let path = format!("{}/next-release/{}.csv", stable_dir, name);
+    let data_path = Path::new(&path);
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
+    }
+    let out_f = File::create(data_path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+
+    writer.write_record(["Test", "Release Failures", "Nightly Failures"])?;
+    for (test, stable_failure) in stable_failures.iter() {
+        let stable_count = stable_failure.failures;
+        let experimental_count = experimental_failures
+            .get(test)
+            .map(|x| x.failures)
+            .unwrap_or(0);
+        if experimental_count < stable_count {
+            writer.write_record([
+                test,
+                &stable_count.to_string(),
+                &experimental_count.to_string(),
+            ])?;
+        }
     }
     Ok(())
 }
@@ -291,14 +1008,18 @@ fn browser_score(browser: &str, columns: &[&str], row: &interop::ScoreRow) -> Re
 
 pub fn write_browser_interop_scores(
     year: u64,
-    browsers: &[&str],
+    channel: BrowserChannel,
     scores: &[interop::ScoreRow],
     interop_year_data: &interop::YearData,
 ) -> Result<()> {
+    let browsers = channel.browsers();
     let browser_columns = interop_columns(&interop_year_data.focus_areas);
 
-    let path = format!("../docs/interop-{}/scores.csv", year);
+    let path = format!("{}/scores.csv", interop_dir(year, channel));
     let data_path = Path::new(&path);
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
+    }
     let out_f = File::create(data_path)?;
     let mut writer = csv::WriterBuilder::new()
         .quote_style(csv::QuoteStyle::NonNumeric)
@@ -328,40 +1049,91 @@ pub fn write_browser_interop_scores(
     Ok(())
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct BugResponse {
-    bugs: Vec<BugData>,
+/// Structured annotations from a bug's user story.
+///
+/// These are written one per line in the form `key: value`, for example
+/// `platform-scheduled: 2025-02-15`.
+#[derive(Debug, Default)]
+struct UserStory {
+    /// Date by which the platform work is scheduled to be done
+    scheduled: Option<time::Date>,
+    owner_team: Option<String>,
+    /// Firefox score for the focus area that counts as done
+    target: Option<f64>,
+    /// Any other annotations, keyed by lowercase key
+    other: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct BugData {
-    alias: String,
-    cf_user_story: String,
-    component: String,
-    id: u64,
-    depends_on: Vec<u64>,
-    product: String,
-    resolution: String,
-    summary: String,
-}
-
-fn get_bug_data(client: &reqwest::blocking::Client, year: u64) -> Result<Option<Vec<BugData>>> {
+impl UserStory {
+    fn parse(user_story: &str) -> UserStory {
+        let mut rv = UserStory::default();
+        for line in user_story.lines() {
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key.is_empty() || value.is_empty() {
+                continue;
+            }
+            match key.as_ref() {
+                "platform-scheduled" => match time::Date::parse(value, &Iso8601::DATE) {
+                    Ok(date) => rv.scheduled = Some(date),
+                    Err(_) => warn!("Invalid platform-scheduled date {}", value),
+                },
+                "owner-team" => rv.owner_team = Some(value.into()),
+                "target" => match value.trim_end_matches('%').parse() {
+                    Ok(target) => rv.target = Some(target),
+                    Err(_) => warn!("Invalid target {}", value),
+                },
+                _ => {
+                    rv.other.insert(key, value.into());
+                }
+            }
+        }
+        rv
+    }
+}
+
+/// Fields requested for interop meta bugs
+const META_BUG_FIELDS: &[&str] = &[
+    "id",
+    "summary",
+    "alias",
+    "product",
+    "component",
+    "resolution",
+    "depends_on",
+    "cf_user_story",
+];
+
+/// Fields requested for the bugs that meta bugs depend on
+const DEPENDENCY_FIELDS: &[&str] = &[
+    "id",
+    "summary",
+    "product",
+    "component",
+    "status",
+    "resolution",
+    "assigned_to",
+    "priority",
+    "severity",
+    "depends_on",
+];
+
+fn get_bug_data(bugzilla: &Bugzilla, year: u64) -> Result<Option<Vec<Bug>>> {
     let data_path = Path::new("../docs/bugzilla_bugs.json");
     let bugs: BTreeMap<String, Vec<String>> = serde_json::from_reader(fs::File::open(data_path)?)?;
     if let Some(aliases) = bugs.get(&year.to_string()) {
-        let bugzilla_url = format!("https://bugzilla.mozilla.org/rest/bug?alias={}&include_fields=id,summary,alias,product,component,resolution,depends_on,cf_user_story", aliases.join(","));
-        let resp = network::get(client, &bugzilla_url, None)?;
-        debug!("get_bug_data got: {}", resp);
-        let bug_data: BugResponse = serde_json::from_str(&resp)?;
-        let mut bugs = bug_data.bugs;
-        bugs.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut bugs = bugzilla.bugs_by_alias(aliases, META_BUG_FIELDS)?;
+        debug!("get_bug_data got: {:?}", bugs);
+        bugs.sort_by_key(|a| a.id);
         Ok(Some(bugs))
     } else {
         Ok(None)
     }
 }
 
-fn write_bugzilla_data(year: u64, bug_data: &[BugData]) -> Result<()> {
+fn write_bugzilla_data(year: u64, bug_data: &[Bug]) -> Result<()> {
     let path = format!("../docs/interop-{}/bugs.csv", year);
     let data_path = Path::new(&path);
     let out_f = File::create(data_path)?;
@@ -377,13 +1149,17 @@ fn write_bugzilla_data(year: u64, bug_data: &[BugData]) -> Result<()> {
         "Resolution",
         "User Story",
         "Depends On",
+        "Scheduled",
+        "Owner Team",
+        "Target",
     ];
     writer.write_record(headers)?;
 
     for bug_data in bug_data.iter() {
+        let user_story = UserStory::parse(&bug_data.cf_user_story);
         writer.write_record([
             &bug_data.id.to_string(),
-            &bug_data.alias,
+            bug_data.alias(),
             &bug_data.summary,
             &bug_data.product,
             &bug_data.component,
@@ -395,69 +1171,525 @@ fn write_bugzilla_data(year: u64, bug_data: &[BugData]) -> Result<()> {
                 .map(|x| x.to_string())
                 .collect::<Vec<String>>()
                 .join(";"),
+            &user_story
+                .scheduled
+                .map(|x| x.to_string())
+                .unwrap_or_default(),
+            &user_story.owner_team.unwrap_or_default(),
+            &user_story.target.map(|x| x.to_string()).unwrap_or_default(),
         ])?;
     }
 
     Ok(())
 }
 
-pub fn run() -> Result<()> {
-    let client = network::client()?;
-    let fyi = Wptfyi::new(None);
+/// Firefox score used as the target when a meta bug doesn't set one
+const DEFAULT_SCORE_TARGET: f64 = 90.;
+
+/// Write the focus areas whose scheduled date has passed but where the
+/// latest Firefox score is still below target.
+///
+/// Meta bugs are matched to focus areas by alias, using the
+/// meta_bug_focus_areas config where the alias differs from the focus
+/// area name.
+fn write_overdue_focus_areas(
+    interop_year: &InteropYear,
+    today: time::Date,
+    bug_data: &[Bug],
+    scores: &[interop::ScoreRow],
+) -> Result<()> {
+    let path = format!("../docs/interop-{}/overdue.csv", interop_year.year);
+    let out_f = File::create(Path::new(&path))?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record([
+        "Focus Area",
+        "Bug",
+        "Scheduled",
+        "Owner Team",
+        "Firefox Score",
+        "Target",
+    ])?;
 
-    let runs = get_run_data(&fyi, &client)?;
-    let run_ids = latest_runs(&runs)?
+    let latest_scores = match scores.last() {
+        Some(row) => row,
+        None => return Ok(()),
+    };
+    for bug in bug_data.iter() {
+        let focus_area = interop_year.meta_bug_focus_area(bug.alias());
+        let score = match latest_scores
+            .get(&format!("firefox-{}", focus_area))
+            .and_then(|x| x.parse::<f64>().ok())
+        {
+            Some(score) => score / 10.,
+            None => {
+                warn!(
+                    "No score column for meta bug {} (focus area {})",
+                    bug.alias(),
+                    focus_area
+                );
+                continue;
+            }
+        };
+        let user_story = UserStory::parse(&bug.cf_user_story);
+        let scheduled = match user_story.scheduled {
+            Some(scheduled) if scheduled < today => scheduled,
+            _ => continue,
+        };
+        let target = user_story.target.unwrap_or(DEFAULT_SCORE_TARGET);
+        if score < target {
+            writer.write_record([
+                focus_area,
+                &bug.id.to_string(),
+                &scheduled.to_string(),
+                user_story.owner_team.as_deref().unwrap_or_default(),
+                &format!("{:.1}", score),
+                &target.to_string(),
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+/// Fetch every bug that the meta bugs depend on, directly or indirectly.
+///
+/// The tree is walked one level at a time, requesting each level
+/// together, and bugs that have already been seen are skipped so that
+/// dependency cycles terminate.
+fn get_dependencies(bugzilla: &Bugzilla, meta_bugs: &[Bug]) -> Result<BTreeMap<u64, Bug>> {
+    let mut seen: BTreeSet<u64> = meta_bugs.iter().map(|x| x.id).collect();
+    let mut dependencies = BTreeMap::new();
+    let mut pending: Vec<u64> = meta_bugs
         .iter()
-        .map(|x| x.id)
-        .collect::<Vec<i64>>();
+        .flat_map(|x| x.depends_on.iter().copied())
+        .filter(|x| seen.insert(*x))
+        .collect();
 
-    let interop_data = get_interop_data(&fyi, &client)?;
+    while !pending.is_empty() {
+        let mut next = Vec::new();
+        for bug in bugzilla.bugs_by_id(&pending, DEPENDENCY_FIELDS)? {
+            next.extend(bug.depends_on.iter().copied().filter(|x| seen.insert(*x)));
+            dependencies.insert(bug.id, bug);
+        }
+        debug!(
+            "Fetched {} dependencies, {} more to fetch",
+            dependencies.len(),
+            next.len()
+        );
+        pending = next;
+    }
+    Ok(dependencies)
+}
+
+/// All the known bugs in the dependency tree of a bug, with the bug each
+/// was first reached from
+fn dependency_tree(root: &Bug, dependencies: &BTreeMap<u64, Bug>) -> Vec<(u64, u64)> {
+    let mut seen = BTreeSet::new();
+    let mut rv = Vec::new();
+    let mut pending: Vec<(u64, u64)> = root.depends_on.iter().map(|x| (root.id, *x)).collect();
+    while let Some((parent, bug_id)) = pending.pop() {
+        if !seen.insert(bug_id) {
+            continue;
+        }
+        if let Some(bug) = dependencies.get(&bug_id) {
+            rv.push((parent, bug_id));
+            pending.extend(bug.depends_on.iter().map(|x| (bug_id, *x)));
+        }
+    }
+    rv.sort();
+    rv
+}
+
+fn write_dependency_data(
+    year: u64,
+    meta_bugs: &[Bug],
+    dependencies: &BTreeMap<u64, Bug>,
+) -> Result<()> {
+    let path = format!("../docs/interop-{}/bug-dependencies.csv", year);
+    let out_f = File::create(Path::new(&path))?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record([
+        "Meta Bug",
+        "Parent",
+        "Bug",
+        "Title",
+        "Status",
+        "Resolution",
+        "Assignee",
+        "Priority",
+        "Severity",
+    ])?;
+
+    let path = format!("../docs/interop-{}/bug-progress.csv", year);
+    let out_f = File::create(Path::new(&path))?;
+    let mut progress_writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    progress_writer.write_record(["Meta Bug", "Alias", "Open", "Fixed", "Closed", "Total"])?;
+
+    for meta_bug in meta_bugs.iter() {
+        let mut open = 0;
+        let mut fixed = 0;
+        let tree = dependency_tree(meta_bug, dependencies);
+        for (parent, bug_id) in tree.iter() {
+            let bug = &dependencies[bug_id];
+            if bug.is_open() {
+                open += 1;
+            } else if bug.is_fixed() {
+                fixed += 1;
+            }
+            writer.write_record([
+                &meta_bug.id.to_string(),
+                &parent.to_string(),
+                &bug.id.to_string(),
+                &bug.summary,
+                &bug.status,
+                &bug.resolution,
+                &bug.assigned_to,
+                &bug.priority,
+                &bug.severity,
+            ])?;
+        }
+        progress_writer.write_record([
+            &meta_bug.id.to_string(),
+            meta_bug.alias(),
+            &open.to_string(),
+            &fixed.to_string(),
+            &(tree.len() - open - fixed).to_string(),
+            &tree.len().to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct BugCounts {
+    open: u64,
+    closed: u64,
+}
+
+impl BugCounts {
+    fn add(&mut self, bug: &Bug) {
+        if bug.is_open() {
+            self.open += 1;
+        } else {
+            self.closed += 1;
+        }
+    }
+}
+
+/// Bug counts for a single day
+#[derive(Debug, Deserialize, Serialize)]
+struct BugSnapshot {
+    date: String,
+    /// Counts for the dependency tree of each meta bug, keyed by alias
+    meta_bugs: BTreeMap<String, BugCounts>,
+    /// Counts for all the dependencies, keyed by product :: component
+    components: BTreeMap<String, BugCounts>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct BugHistory {
+    end_date: Option<String>,
+    snapshots: Vec<BugSnapshot>,
+}
+
+fn load_bug_history(path: &Path) -> Result<BugHistory> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(BugHistory::default())
+    }
+}
+
+/// Record today's open and closed bug counts in the year's bug history.
+///
+/// There is one snapshot per day; running again on the same day replaces
+/// that day's snapshot.
+fn update_bug_history(
+    interop_year: &InteropYear,
+    today: time::Date,
+    meta_bugs: &[Bug],
+    dependencies: &BTreeMap<u64, Bug>,
+) -> Result<()> {
+    let path = format!("../docs/interop-{}/bug-history.json", interop_year.year);
+    let data_path = Path::new(&path);
+    let mut history = load_bug_history(data_path)?;
+
+    let mut snapshot = BugSnapshot {
+        date: today.to_string(),
+        meta_bugs: BTreeMap::new(),
+        components: BTreeMap::new(),
+    };
+    let mut all_bugs = BTreeSet::new();
+    for meta_bug in meta_bugs.iter() {
+        let counts = snapshot
+            .meta_bugs
+            .entry(meta_bug.alias().to_owned())
+            .or_default();
+        for (_, bug_id) in dependency_tree(meta_bug, dependencies) {
+            counts.add(&dependencies[&bug_id]);
+            all_bugs.insert(bug_id);
+        }
+    }
+    for bug_id in all_bugs {
+        let bug = &dependencies[&bug_id];
+        snapshot
+            .components
+            .entry(format!("{} :: {}", bug.product, bug.component))
+            .or_default()
+            .add(bug);
+    }
+
+    if history.snapshots.last().map(|x| &x.date) == Some(&snapshot.date) {
+        history.snapshots.pop();
+    }
+    history.snapshots.push(snapshot);
+    history.end_date = interop_year.end_date.map(|x| x.to_string());
+
+    let out_f = File::create(data_path)?;
+    serde_json::to_writer(out_f, &history)?;
+    Ok(())
+}
+
+/// Number of tests listed in the top fixes report
+const TOP_FIXES_COUNT: usize = 50;
+
+/// Write the failing tests whose fix would most improve the overall
+/// Firefox interop score.
+fn write_top_fixes(
+    year: u64,
+    channel: BrowserChannel,
+    failures: &BTreeMap<String, TestFailures>,
+) -> Result<()> {
+    let mut all_failures = failures
+        .iter()
+        .flat_map(|(name, tests)| {
+            tests
+                .iter()
+                .map(move |(test, failure)| (name, test, failure))
+        })
+        .collect::<Vec<_>>();
+    all_failures.sort_by(|(_, _, a), (_, _, b)| b.impact.interop.total_cmp(&a.impact.interop));
+
+    let path = format!("{}/top-fixes.csv", interop_dir(year, channel));
+    let data_path = Path::new(&path);
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
+    }
+    let out_f = File::create(data_path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+
+    writer.write_record([
+        "Focus Area",
+        "Test",
+        "Firefox Failures",
+        "Focus Area Gain",
+        "Interop Gain",
+    ])?;
+    for (name, test, failure) in all_failures.into_iter().take(TOP_FIXES_COUNT) {
+        writer.write_record([
+            name,
+            test,
+            &failure.failures.to_string(),
+            &format!("{:.4}", failure.impact.focus_area),
+            &format!("{:.4}", failure.impact.interop),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Data for a single channel that's used when comparing channels
+struct ChannelData {
+    failures: BTreeMap<String, TestFailures>,
+    scores: Vec<interop::ScoreRow>,
+}
+
+fn write_channel_data(
+    ctx: &InteropContext,
+    year: u64,
+    categories_by_name: &BTreeMap<String, &Category>,
+    interop_year_data: &interop::YearData,
+) -> Result<ChannelData> {
+    info!("Processing Interop {} {}", year, ctx.channel);
+    let mut failures = BTreeMap::new();
+    for (name, focus_area) in interop_year_data.focus_areas.iter() {
+        if !focus_area.counts_toward_score {
+            continue;
+        }
+        let tests = FocusAreaTests::from_labels(focus_area_labels(categories_by_name, name)?);
+        failures.insert(name.clone(), write_focus_area(ctx, name, &tests)?);
+    }
+
+    write_top_fixes(year, ctx.channel, &failures)?;
+
+    let scores = get_interop_scores(ctx.fyi, ctx.client, year, ctx.channel)?;
+    write_browser_interop_scores(year, ctx.channel, &scores, interop_year_data)?;
+    Ok(ChannelData { failures, scores })
+}
+
+pub fn run(wpt: &WptData) -> Result<()> {
+    let client = &wpt.client;
+    let fyi = &wpt.fyi;
+
+    let interop_metadata = get_interop_data(fyi, client)?;
+    let interop_categories = get_interop_categories(fyi, client)?;
     let today = time::OffsetDateTime::now_utc().date();
+    let year_config = load_year_config(Path::new("../docs/interop_years.json"))?;
+    let requested_years = requested_years()?;
 
-    for interop_year in interop_years().iter() {
-        if interop_year.end_date < today {
+    let subtest_detail = env::var_os("AREWEWPTYET_SUBTEST_DETAIL").is_some();
+    let bug_links = BugLinkParser::default();
+    let bugzilla = Bugzilla::from_env(client)?;
+
+    for interop_year in interop_years(&interop_metadata, &year_config, today)?.iter() {
+        if !interop_year.active && !requested_years.contains(&interop_year.year) {
+            debug!(
+                "Skipping inactive Interop {} (end date {:?})",
+                interop_year.year, interop_year.end_date
+            );
             continue;
         }
         let year = interop_year.year;
         info!("Processing Interop {}", year);
-        let interop_year_data = interop_data
+        let interop_year_data = interop_metadata
+            .years
             .get(&year.to_string())
             .ok_or_else(|| anyhow!("Failed to get Interop metadata"))?;
 
-        let interop_categories = get_interop_categories(&fyi, &client)?;
-
         let interop_year_categories = interop_categories
             .get(&year.to_string())
             .ok_or_else(|| anyhow!("Failed to get Interop categories"))?;
         let categories_by_name = interop_year_categories.by_name();
 
-        let metadata = get_metadata(&fyi, &client)?;
+        let mut channel_pairs = vec![(BrowserChannel::Experimental, BrowserChannel::Stable)];
+        if interop_metadata.is_mobile_year(year) {
+            channel_pairs.push((
+                BrowserChannel::MobileExperimental,
+                BrowserChannel::MobileStable,
+            ));
+        }
 
-        for (name, focus_area) in interop_year_data.focus_areas.iter() {
-            write_focus_area(
-                &fyi,
-                &client,
-                year,
-                name,
-                focus_area,
-                &run_ids,
-                &categories_by_name,
-                &metadata,
-            )?;
+        let mut channel_data = BTreeMap::new();
+        for channel in channel_pairs.iter().flat_map(|(x, y)| [*x, *y]) {
+            let data = wpt.channel_runs(channel).and_then(|runs| {
+                let ctx = InteropContext {
+                    fyi,
+                    client,
+                    channel,
+                    dir: interop_dir(year, channel),
+                    runs: &runs,
+                    metadata: &wpt.metadata,
+                    bug_links: &bug_links,
+                    subtest_detail,
+                    focus_area_count: interop_columns(&interop_year_data.focus_areas).len(),
+                };
+                write_channel_data(&ctx, year, &categories_by_name, interop_year_data)
+            });
+            match data {
+                Ok(data) => {
+                    channel_data.insert(channel, data);
+                }
+                // wpt.fyi doesn't always have a complete set of runs or
+                // scores for the other channels, so don't let that stop the
+                // desktop experimental outputs and bug data being written
+                Err(err) if channel != BrowserChannel::Experimental => {
+                    warn!("Skipping Interop {} {}: {:?}", year, channel, err);
+                }
+                Err(err) => return Err(err),
+            }
         }
 
-        let scores =
-            get_interop_scores(&fyi, &client, year, interop::BrowserChannel::Experimental)?;
-        write_browser_interop_scores(
-            year,
-            &["firefox", "chrome", "safari"],
-            &scores,
-            interop_year_data,
-        )?;
+        for (experimental_channel, stable_channel) in channel_pairs {
+            let (experimental_data, stable_data) = match (
+                channel_data.get(&experimental_channel),
+                channel_data.get(&stable_channel),
+            ) {
+                (Some(experimental_data), Some(stable_data)) => (experimental_data, stable_data),
+                _ => continue,
+            };
+            let experimental_failures = &experimental_data.failures;
+            for (name, stable_failures) in stable_data.failures.iter() {
+                if let Some(experimental_failures) = experimental_failures.get(name) {
+                    write_next_release_fixes(
+                        &interop_dir(year, stable_channel),
+                        name,
+                        stable_failures,
+                        experimental_failures,
+                    )?;
+                }
+            }
+        }
 
-        if let Some(bug_data) = get_bug_data(&client, year)? {
+        if let Some(bug_data) = get_bug_data(&bugzilla, year)? {
             write_bugzilla_data(year, &bug_data)?;
+            write_overdue_focus_areas(
+                interop_year,
+                today,
+                &bug_data,
+                &channel_data[&BrowserChannel::Experimental].scores,
+            )?;
+            let dependencies = get_dependencies(&bugzilla, &bug_data)?;
+            write_dependency_data(year, &bug_data, &dependencies)?;
+            update_bug_history(interop_year, today, &bug_data, &dependencies)?;
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_prefixes() {
+        assert_eq!(glob_prefix("/css/css-grid/**"), Some("/css/css-grid/"));
+        assert_eq!(
+            glob_prefix("/css/css-grid/grid-*.html"),
+            Some("/css/css-grid/grid-")
+        );
+        assert_eq!(
+            glob_prefix("/dom/nodes/Node-cloneNode.html"),
+            Some("/dom/nodes/Node-cloneNode.html")
+        );
+        assert_eq!(glob_prefix("/css*/**"), None);
+        assert_eq!(glob_prefix("/**"), None);
+        assert_eq!(glob_prefix("**/grid-*.html"), None);
+        assert_eq!(glob_prefix("*/css-grid/**"), None);
+        assert_eq!(glob_prefix("css/css-grid/**"), None);
+    }
+
+    #[test]
+    fn glob_matches() {
+        assert!(glob_match("/css/css-grid/**", "/css/css-grid/a/b.html"));
+        assert!(glob_match("/css/*/grid.html", "/css/css-grid/grid.html"));
+        assert!(!glob_match("/css/*/grid.html", "/css/css-grid/a/grid.html"));
+        assert!(glob_match(
+            "/css/**/grid-*.html",
+            "/css/css-grid/a/grid-1.html"
+        ));
+        assert!(!glob_match(
+            "/css/**/grid-*.html",
+            "/css/css-grid/a/grid/1.html"
+        ));
+    }
+
+    #[test]
+    fn unsearchable_globs() {
+        let tests = FocusAreaTests {
+            paths: vec!["/css/css-grid/**", "**/grid-*.html"],
+            ..Default::default()
+        };
+        assert!(tests.check_paths().is_err());
+        let tests = FocusAreaTests {
+            paths: vec!["/css/css-grid/**"],
+            ..Default::default()
+        };
+        assert!(tests.check_paths().is_ok());
+    }
+}
@@ -1,32 +1,87 @@
-use crate::network::{self, get};
-use anyhow::Result;
+use crate::github::{self, GitHub};
+use crate::network::{self, get, post};
+use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
-use log::{debug, info};
+use log::{debug, info, warn};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet};
+use std::convert::TryFrom;
+use std::env;
 use std::fs::File;
-use std::path::Path;
-use time::serde::iso8601;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use thiserror::Error;
+use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 
 lazy_static! {
-    static ref BACKOUT_RE: Regex = Regex::new(r"Backed out \d+ changeset").unwrap();
-    static ref CHANGESET_RE: Regex = Regex::new(r"Backed out changeset ([0-9a-fA-F]+)").unwrap();
+    static ref BACKOUT_RE: Regex = Regex::new(r"^Backed out (?:\d+ )?changesets?(.*)").unwrap();
+    static ref CHANGESET_RE: Regex = Regex::new(r"^Backed out changeset ([0-9a-fA-F]+)").unwrap();
+    static ref REVERT_RE: Regex = Regex::new(r"This reverts commit ([0-9a-fA-F]+)").unwrap();
+    static ref BUG_RE: Regex = Regex::new(r"(?i)\bbug \d+").unwrap();
+    static ref REV_RE: Regex = Regex::new(r"\b[0-9a-fA-F]{7,40}\b").unwrap();
     static ref UPDATE_RE: Regex =
         Regex::new(r".*Update web-platform-tests to ([0-9a-fA-F]+)").unwrap();
+    static ref MERGE_PR_RE: Regex = Regex::new(r"^Merge pull request #(\d+)").unwrap();
+    static ref SQUASH_PR_RE: Regex = Regex::new(r"\(#(\d+)\)\s*$").unwrap();
+    static ref GECKO_BUG_RE: Regex = Regex::new(r"(?i)^bug (\d+)").unwrap();
+    static ref GECKO_COMMIT_RE: Regex = Regex::new(r"(?m)^gecko-commit: ([0-9a-fA-F]+)").unwrap();
+    static ref BUGZILLA_URL_RE: Regex = Regex::new(r"(?m)^bugzilla-url: \S*[?&]id=(\d+)").unwrap();
 }
 
+/// Shortest revision prefix accepted in a backout message
+const MIN_REV_LEN: usize = 7;
+
+const HG_LOG_URL: &str = "https://hg.mozilla.org/integration/autoland/json-log";
+const SYNC_PATH: &str = "testing/web-platform/meta/mozilla-sync";
+
+/// Number of commits to request per json-log page
+const HG_PAGE_SIZE: usize = 100;
+
+/// Maximum number of json-log pages to fetch when looking for known history
+const MAX_HG_PAGES: usize = 50;
+
+const TESTS_PATH: &str = "testing/web-platform/tests";
+
+/// Label wpt-sync adds to the PRs it exports from Gecko
+const EXPORT_LABEL: &str = "mozilla:gecko-sync";
+
+/// How far back to look for Gecko commits whose export may have merged
+/// since the last run
+const EXPORT_WINDOW_DAYS: i64 = 90;
+
+/// How long before an exported PR merged to look for its Gecko commit
+const EXPORT_LOOKBACK_DAYS: i64 = 14;
+
+const WPT_REPO: &str = "web-platform-tests/wpt";
+
+/// Where GitHub responses are cached between runs, outside docs/ so the cache
+/// isn't committed
+const DEFAULT_GITHUB_CACHE: &str = "../.github-cache.json";
+
+const SEARCH_PAGE_SIZE: usize = 100;
+
+/// GitHub's search API returns at most 10 pages of 100 results
+const MAX_SEARCH_PAGES: usize = 10;
+
+const COMPARE_PAGE_SIZE: usize = 100;
+
+/// Maximum number of pages of commits to list between two sync points
+const MAX_COMPARE_PAGES: usize = 20;
+
 #[derive(Debug, Deserialize)]
 pub struct HgLog {
     entries: Vec<HgLogEntry>,
 }
 
-#[derive(Debug, Deserialize)]
-struct GitHubPr {
+/// A wpt PR and the commit it was merged as
+#[derive(Debug, Clone)]
+struct MergedPr {
     number: u64,
-    #[serde(with = "iso8601")]
-    closed_at: OffsetDateTime,
+    sha: String,
+    merged_at: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,6 +91,25 @@ pub struct HgLogEntry {
     pushdate: (f64, i64),
 }
 
+/// A Gecko commit, from either hg or git
+#[derive(Debug)]
+pub struct GeckoCommit {
+    node: String,
+    desc: String,
+    /// Push time for hg; commit time for git, which doesn't record pushes
+    push_date: i64,
+}
+
+impl From<HgLogEntry> for GeckoCommit {
+    fn from(entry: HgLogEntry) -> GeckoCommit {
+        GeckoCommit {
+            node: entry.node,
+            desc: entry.desc,
+            push_date: entry.pushdate.0 as i64,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct GeckoSyncPoint {
     wpt_rev: String,
@@ -58,25 +132,57 @@ impl SyncData {
 #[derive(Debug, Deserialize, Serialize)]
 struct SyncPointWptRev {
     wpt_rev: String,
+    /// The PR that was merged as wpt_rev
     wpt_pr: u64,
     wpt_merge_time: i64,
     gecko_push_time: i64,
+    /// All the PRs merged since the previous sync point
+    #[serde(default)]
+    wpt_prs: Vec<u64>,
 }
 
-impl From<HgLogEntry> for GeckoSyncPoint {
-    fn from(commit: HgLogEntry) -> GeckoSyncPoint {
-        // This will panic if the commit doesn't have the correct metadata
+/// Reasons a Gecko commit can't be used
+#[derive(Debug, Error)]
+pub enum GeckoCommitError {
+    #[error("Commit message doesn't name a wpt revision")]
+    MissingWptRev,
+    #[error("Revision {0} is too short to identify a commit")]
+    ShortRevision(String),
+}
+
+/// A Gecko commit that was skipped because it couldn't be understood
+#[derive(Debug)]
+pub struct RejectedCommit {
+    node: String,
+    summary: String,
+    error: GeckoCommitError,
+}
+
+impl RejectedCommit {
+    fn new(commit: &GeckoCommit, error: GeckoCommitError) -> RejectedCommit {
+        warn!("Skipping Gecko commit {}: {}", commit.node, error);
+        RejectedCommit {
+            node: commit.node.clone(),
+            summary: commit.desc.lines().next().unwrap_or_default().to_owned(),
+            error,
+        }
+    }
+}
+
+impl TryFrom<&GeckoCommit> for GeckoSyncPoint {
+    type Error = GeckoCommitError;
+
+    fn try_from(commit: &GeckoCommit) -> Result<GeckoSyncPoint, GeckoCommitError> {
         let wpt_rev = UPDATE_RE
             .captures(&commit.desc)
-            .expect("Commit desc must match UPDATE_RE")
-            .get(1)
-            .expect("Capture group must be present")
+            .and_then(|captures| captures.get(1))
+            .ok_or(GeckoCommitError::MissingWptRev)?
             .as_str()
             .to_owned();
-        GeckoSyncPoint {
+        Ok(GeckoSyncPoint {
             wpt_rev,
-            push_date: commit.pushdate.0 as i64,
-        }
+            push_date: commit.push_date,
+        })
     }
 }
 
@@ -97,6 +203,10 @@ impl LandingData {
         }
     }
 
+    fn contains(&self, wpt_rev: &str) -> bool {
+        self.have_shas.contains(wpt_rev)
+    }
+
     fn missing(&self, sync_points: impl Iterator<Item = GeckoSyncPoint>) -> Vec<GeckoSyncPoint> {
         let mut rv = Vec::new();
         for sync_point in sync_points {
@@ -107,89 +217,360 @@ impl LandingData {
         rv
     }
 
-    fn insert(&mut self, sync_point: GeckoSyncPoint, pr: GitHubPr) {
+    /// The wpt revision of the last landing pushed before the given time
+    fn previous_rev(&self, push_date: i64) -> Option<&str> {
+        self.sync_data
+            .landings
+            .iter()
+            .filter(|x| x.gecko_push_time < push_date)
+            .max_by_key(|x| x.gecko_push_time)
+            .map(|x| x.wpt_rev.as_str())
+    }
+
+    fn insert(&mut self, sync_point: GeckoSyncPoint, pr: &MergedPr, wpt_prs: Vec<u64>) {
         let data = SyncPointWptRev {
             wpt_rev: sync_point.wpt_rev,
             wpt_pr: pr.number,
-            wpt_merge_time: pr.closed_at.unix_timestamp(),
+            wpt_merge_time: pr.merged_at,
             gecko_push_time: sync_point.push_date,
+            wpt_prs,
         };
         self.have_shas.insert(data.wpt_rev.clone());
         self.sync_data.landings.push(data);
     }
 }
 
-fn filter_backouts(commits: Vec<HgLogEntry>) -> Vec<HgLogEntry> {
-    let mut backed_out: HashMap<String, HashSet<String>> = HashMap::new();
-    let mut filtered_commits = Vec::with_capacity(commits.len());
-
-    for commit in commits.into_iter() {
-        if BACKOUT_RE.is_match(&commit.desc) {
-            for line in commit.desc.lines() {
-                let changeset = CHANGESET_RE.captures(line);
-                if let Some(captures) = changeset {
-                    let changeset_rev = captures
-                        .get(1)
-                        .expect("Couldn't get changeset from rev")
-                        .as_str()
-                        .to_owned();
-                    let short_rev = changeset_rev[0..12].to_owned();
-                    backed_out
-                        .entry(short_rev)
-                        .and_modify(|v| {
-                            v.insert(changeset_rev.clone());
-                        })
-                        .or_insert_with(|| {
-                            let mut entry = HashSet::new();
-                            entry.insert(changeset_rev);
-                            entry
-                        });
+/// Revisions backed out by a commit, in lowercase.
+///
+/// These come from hg backout lines, which may name several revisions, or
+/// git revert trailers. Revisions can be abbreviated.
+fn backed_out_revs(desc: &str) -> Result<Vec<String>, GeckoCommitError> {
+    let mut revs = Vec::new();
+    for line in desc.lines().map(|x| x.trim()) {
+        let line_revs = if let Some(captures) = BACKOUT_RE.captures(line) {
+            if let Some(rev) = CHANGESET_RE.captures(line).and_then(|x| x.get(1)) {
+                if rev.as_str().len() < MIN_REV_LEN {
+                    return Err(GeckoCommitError::ShortRevision(rev.as_str().into()));
                 }
             }
+            // Bug numbers look like short revisions so remove them first
+            let rest = BUG_RE.replace_all(captures.get(1).map_or("", |x| x.as_str()), "");
+            REV_RE
+                .find_iter(&rest)
+                .map(|x| x.as_str())
+                .filter(|rev| rev.chars().any(|c| c.is_ascii_digit()))
+                .map(|rev| rev.to_lowercase())
+                .collect()
+        } else if let Some(rev) = REVERT_RE.captures(line).and_then(|x| x.get(1)) {
+            if rev.as_str().len() < MIN_REV_LEN {
+                return Err(GeckoCommitError::ShortRevision(rev.as_str().into()));
+            }
+            vec![rev.as_str().to_lowercase()]
         } else {
-            let short_rev = &commit.node[0..12];
-            let is_backed_out = if backed_out.contains_key(short_rev) {
-                let full_revs = backed_out.get_mut(short_rev).unwrap();
-                full_revs.remove(&commit.node)
-            } else {
-                false
-            };
-            if !is_backed_out {
-                filtered_commits.push(commit);
+            continue;
+        };
+        revs.extend(line_revs);
+    }
+    Ok(revs)
+}
+
+/// Remove the first revision in `revs` that's a prefix of `node`,
+/// returning the associated value.
+fn take_matching<T>(revs: &mut Vec<(String, T)>, node: &str) -> Option<T> {
+    revs.iter()
+        .position(|(rev, _)| node.starts_with(rev.as_str()))
+        .map(|idx| revs.remove(idx).1)
+}
+
+/// Remove backed out commits, given commits in newest-first order.
+///
+/// git reverts are treated the same way as hg backouts. A backout that is
+/// itself backed out has no effect, and the commits it backed out count
+/// as relanded when the second backout was pushed.
+fn filter_backouts(
+    commits: Vec<GeckoCommit>,
+    rejected: &mut Vec<RejectedCommit>,
+) -> Vec<GeckoCommit> {
+    // Revisions backed out by a newer commit, with the backout's node and
+    // push date
+    let mut backed_out: Vec<(String, (String, i64))> = Vec::new();
+    // Revisions relanded by backing out their backout, with the reland's
+    // node and push date
+    let mut relanded: Vec<(String, (String, i64))> = Vec::new();
+    let mut filtered_commits = Vec::with_capacity(commits.len());
+
+    for mut commit in commits.into_iter() {
+        let node = commit.node.to_lowercase();
+        let backout_revs = match backed_out_revs(&commit.desc) {
+            Ok(revs) => revs,
+            Err(err) => {
+                rejected.push(RejectedCommit::new(&commit, err));
+                continue;
             }
+        };
+
+        if let Some((backout, backout_date)) = take_matching(&mut backed_out, &node) {
+            debug!("{} was backed out by {}", commit.node, backout);
+            relanded.extend(
+                backout_revs
+                    .into_iter()
+                    .map(|rev| (rev, (backout.clone(), backout_date))),
+            );
+            continue;
+        }
+
+        if !backout_revs.is_empty() {
+            debug!("{} backs out {}", commit.node, backout_revs.join(", "));
+            backed_out.extend(
+                backout_revs
+                    .into_iter()
+                    .map(|rev| (rev, (commit.node.clone(), commit.push_date))),
+            );
+            continue;
         }
+
+        if let Some((reland, push_date)) = take_matching(&mut relanded, &node) {
+            debug!("{} was relanded by {}", commit.node, reland);
+            commit.push_date = push_date;
+        } else {
+            debug!("{} landed", commit.node);
+        }
+        filtered_commits.push(commit);
+    }
+    for (rev, (backout, _)) in backed_out {
+        debug!("{} backs out {} which isn't a sync commit", backout, rev);
     }
     filtered_commits
 }
 
-fn filter_update(commit: &HgLogEntry) -> bool {
+fn filter_update(commit: &GeckoCommit) -> bool {
     UPDATE_RE.is_match(&commit.desc)
 }
 
-pub fn extract_sync_points(sync_commits: &str) -> Result<impl Iterator<Item = GeckoSyncPoint>> {
-    let sync_commits: HgLog = serde_json::from_str(sync_commits)?;
-    Ok(filter_backouts(sync_commits.entries)
-        .into_iter()
-        .filter(filter_update)
-        .map(|x| x.into()))
+/// Get the sync points from a list of commits, adding any commits that
+/// can't be understood to `rejected`.
+///
+/// If the same wpt revision landed more than once, e.g. because it was
+/// relanded without backing out the original, the newest landing is used.
+pub fn extract_sync_points(
+    sync_commits: Vec<GeckoCommit>,
+    rejected: &mut Vec<RejectedCommit>,
+) -> Vec<GeckoSyncPoint> {
+    let commits = filter_backouts(sync_commits, rejected);
+    let mut seen = HashSet::new();
+    commits
+        .iter()
+        .filter(|commit| filter_update(commit))
+        .filter_map(|commit| match GeckoSyncPoint::try_from(commit) {
+            Ok(sync_point) => Some(sync_point),
+            Err(err) => {
+                rejected.push(RejectedCommit::new(commit, err));
+                None
+            }
+        })
+        .filter(|sync_point| {
+            let is_new = seen.insert(sync_point.wpt_rev.clone());
+            if !is_new {
+                debug!("Ignoring earlier landing of {}", sync_point.wpt_rev);
+            }
+            is_new
+        })
+        .collect()
 }
-fn get_sync_commits(client: &reqwest::blocking::Client) -> Result<String> {
-    get(client,
-            "https://hg.mozilla.org/integration/autoland/json-log/tip/testing/web-platform/meta/mozilla-sync",
-            None)
+
+fn write_rejected_commits(path: &Path, rejected: &[RejectedCommit]) -> Result<()> {
+    let out_f = File::create(path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record(["Commit", "Summary", "Reason"])?;
+    for commit in rejected.iter() {
+        writer.write_record([&commit.node, &commit.summary, &commit.error.to_string()])?;
+    }
+    Ok(())
 }
 
-fn get_pr_for_rev(client: &reqwest::blocking::Client, wpt_rev: &str) -> Result<String> {
-    let url = format!(
-        "https://api.github.com/repos/web-platform-tests/wpt/commits/{}/pulls",
-        wpt_rev
-    );
-    let mut headers = reqwest::header::HeaderMap::new();
-    headers.insert(
-        "Accept",
-        "application/vnd.github.groot-preview+json".parse().unwrap(),
-    );
-    get(client, &url, Some(headers))
+fn parse_hg_log(data: &str) -> Result<Vec<GeckoCommit>> {
+    let log: HgLog = serde_json::from_str(data)?;
+    Ok(log.entries.into_iter().map(|x| x.into()).collect())
+}
+
+/// Get the commits touching a path from hg, newest first.
+///
+/// json-log only returns a page of history at a time, so keep requesting
+/// older pages until a page contains a commit for which `is_known` is
+/// true, or we reach MAX_HG_PAGES.
+fn get_hg_commits(
+    client: &reqwest::blocking::Client,
+    path: &str,
+    is_known: impl Fn(&GeckoCommit) -> bool,
+) -> Result<Vec<GeckoCommit>> {
+    let mut commits: Vec<GeckoCommit> = Vec::new();
+    let mut rev = "tip".to_owned();
+    let mut pages = 0;
+    loop {
+        let url = format!("{}/{}/{}?revcount={}", HG_LOG_URL, rev, path, HG_PAGE_SIZE);
+        let mut page = parse_hg_log(&get(client, &url, None)?)?;
+        pages += 1;
+        // Each page after the first starts with the last commit we already have
+        if pages > 1 && !page.is_empty() {
+            page.remove(0);
+        }
+        if page.is_empty() {
+            debug!("Reached the start of the history for {}", path);
+            break;
+        }
+        let found_known = page.iter().any(&is_known);
+        commits.extend(page);
+        if found_known {
+            break;
+        }
+        if pages >= MAX_HG_PAGES {
+            info!(
+                "Stopped after {} pages without reaching known history for {}",
+                pages, path
+            );
+            break;
+        }
+        match commits.last() {
+            Some(commit) => rev = commit.node.clone(),
+            None => break,
+        }
+    }
+    if let Some(oldest) = commits.last() {
+        info!(
+            "Fetched {} commits touching {} in {} pages, back to {} pushed at {}",
+            commits.len(),
+            path,
+            pages,
+            oldest.node,
+            oldest.push_date
+        );
+    }
+    Ok(commits)
+}
+
+/// Get the sync commits from hg, back to the first already recorded sync point
+fn get_sync_commits(
+    client: &reqwest::blocking::Client,
+    landings: &LandingData,
+) -> Result<Vec<GeckoCommit>> {
+    get_hg_commits(client, SYNC_PATH, |commit| {
+        UPDATE_RE
+            .captures(&commit.desc)
+            .and_then(|captures| captures.get(1))
+            .map(|wpt_rev| landings.contains(wpt_rev.as_str()))
+            .unwrap_or(false)
+    })
+}
+
+/// Parse the output of `git log` using the format in `get_git_commits`
+fn parse_git_log(data: &str) -> Result<Vec<GeckoCommit>> {
+    data.split('\x1e')
+        .map(|x| x.trim_start_matches('\n'))
+        .filter(|x| !x.is_empty())
+        .map(|record| {
+            let mut fields = record.splitn(3, '\0');
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(node), Some(date), Some(desc)) => Ok(GeckoCommit {
+                    node: node.into(),
+                    desc: desc.into(),
+                    push_date: date.parse()?,
+                }),
+                _ => Err(anyhow!("Unexpected git log record {}", record)),
+            }
+        })
+        .collect()
+}
+
+/// Get the commits touching a path from a local git clone of the Firefox
+/// repository, newest first.
+///
+/// AREWEWPTYET_GECKO_GIT_REF sets the branch to walk, defaulting to HEAD.
+fn get_git_commits(repo: &Path, path: &str, since: Option<&str>) -> Result<Vec<GeckoCommit>> {
+    let git_ref = env::var("AREWEWPTYET_GECKO_GIT_REF").unwrap_or_else(|_| "HEAD".into());
+    let mut cmd = Command::new("git");
+    cmd.arg("-C")
+        .arg(repo)
+        .args(["log", "--format=%H%x00%ct%x00%B%x1e"]);
+    if let Some(since) = since {
+        cmd.arg(format!("--since={}", since));
+    }
+    let output = cmd.args([&git_ref, "--", path]).output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    parse_git_log(&String::from_utf8(output.stdout)?)
+}
+
+/// The PR that was merged as the given commit.
+///
+/// The commit can be associated with several PRs e.g. when it was also
+/// part of another PR's branch, so only accept a merged PR whose merge
+/// commit is the one we're looking for.
+fn merged_pr_for_rev(prs: &[github::PullRequest], wpt_rev: &str) -> Option<MergedPr> {
+    prs.iter()
+        .find_map(|pr| match (&pr.merge_commit_sha, pr.merged_at) {
+            (Some(sha), Some(merged_at)) if sha == wpt_rev => Some(MergedPr {
+                number: pr.number,
+                sha: sha.clone(),
+                merged_at: merged_at.unix_timestamp(),
+            }),
+            _ => None,
+        })
+}
+
+/// The PR number for a commit on the wpt default branch, from either a
+/// merge commit or squash merge message
+fn commit_pr(message: &str) -> Option<u64> {
+    let summary = message.lines().next()?;
+    MERGE_PR_RE
+        .captures(summary)
+        .or_else(|| SQUASH_PR_RE.captures(summary))
+        .and_then(|captures| captures.get(1))
+        .and_then(|pr| pr.as_str().parse().ok())
+}
+
+/// Get the PRs merged after `base` up to and including `head`, in merge order
+fn get_prs_in_range(github: &GitHub, base: &str, head: &str) -> Result<Vec<MergedPr>> {
+    let mut prs: Vec<MergedPr> = Vec::new();
+    for page in 1..=MAX_COMPARE_PAGES {
+        let compare = github.compare(WPT_REPO, base, head, COMPARE_PAGE_SIZE, page)?;
+        let page_len = compare.commits.len();
+        for commit in compare.commits {
+            let number = match commit_pr(&commit.commit.message) {
+                Some(number) => number,
+                None => continue,
+            };
+            let merged_at = commit.commit.committer.date.unix_timestamp();
+            // Rebase merges can reference the same PR from several commits
+            match prs.iter_mut().find(|pr| pr.number == number) {
+                Some(pr) if pr.merged_at <= merged_at => {
+                    pr.sha = commit.sha;
+                    pr.merged_at = merged_at;
+                }
+                Some(_) => {}
+                None => prs.push(MergedPr {
+                    number,
+                    sha: commit.sha,
+                    merged_at,
+                }),
+            }
+        }
+        if page_len < COMPARE_PAGE_SIZE {
+            break;
+        }
+        if page == MAX_COMPARE_PAGES {
+            info!(
+                "Stopped listing commits between {} and {} after {} pages",
+                base, head, page
+            );
+        }
+    }
+    Ok(prs)
 }
 
 fn load_sync_data(path: &Path) -> Result<SyncData> {
@@ -200,32 +581,1246 @@ fn load_sync_data(path: &Path) -> Result<SyncData> {
     }
 }
 
+/// Maximum number of earlier landings to fetch per-PR data for in one run
+const MAX_PR_BACKFILL: usize = 50;
+
+/// Number of runs to try fetching a landing's PRs before giving up on it
+const MAX_BACKFILL_ATTEMPTS: u32 = 3;
+
+/// Failed ranges after which to stop backfilling for this run, in case
+/// GitHub is unavailable
+const MAX_BACKFILL_FAILURES: usize = 5;
+
+/// Latency for a single wpt PR, from its merge to the landing of the
+/// first sync point that included it
+#[derive(Debug, Deserialize, Serialize)]
+struct PrLanding {
+    wpt_pr: u64,
+    wpt_merge_time: i64,
+    wpt_rev: String,
+    gecko_push_time: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PrLatencyData {
+    prs: Vec<PrLanding>,
+    /// Sync points whose PRs have been recorded
+    synced_revs: Vec<String>,
+    /// Sync points whose PRs couldn't be fetched, with the number of attempts
+    #[serde(default)]
+    failed_revs: BTreeMap<String, u32>,
+}
+
+struct PrLatency {
+    data: PrLatencyData,
+    have_revs: HashSet<String>,
+}
+
+impl PrLatency {
+    fn load(path: &Path) -> Result<PrLatency> {
+        let data: PrLatencyData = if let Ok(f) = File::open(path) {
+            serde_json::from_reader(f)?
+        } else {
+            PrLatencyData::default()
+        };
+        let have_revs = data.synced_revs.iter().cloned().collect();
+        Ok(PrLatency { data, have_revs })
+    }
+
+    fn contains(&self, wpt_rev: &str) -> bool {
+        self.have_revs.contains(wpt_rev)
+    }
+
+    fn failed_attempts(&self, wpt_rev: &str) -> u32 {
+        self.data.failed_revs.get(wpt_rev).copied().unwrap_or(0)
+    }
+
+    fn record_failure(&mut self, wpt_rev: &str) {
+        *self.data.failed_revs.entry(wpt_rev.into()).or_default() += 1;
+    }
+
+    fn insert(&mut self, wpt_rev: &str, gecko_push_time: i64, prs: &[MergedPr]) {
+        self.data.failed_revs.remove(wpt_rev);
+        if !self.have_revs.insert(wpt_rev.into()) {
+            return;
+        }
+        self.data.synced_revs.push(wpt_rev.into());
+        self.data.prs.extend(prs.iter().map(|pr| PrLanding {
+            wpt_pr: pr.number,
+            wpt_merge_time: pr.merged_at,
+            wpt_rev: wpt_rev.into(),
+            gecko_push_time,
+        }));
+    }
+
+    fn write(&mut self, path: &Path) -> Result<()> {
+        self.data
+            .prs
+            .sort_by_key(|x| (x.gecko_push_time, x.wpt_merge_time));
+        let out_f = File::create(path)?;
+        serde_json::to_writer(out_f, &self.data)?;
+        Ok(())
+    }
+}
+
+/// The PRs in a landing: those merged in its range, and the PR merged as
+/// its wpt revision
+fn landing_prs(range_prs: &[MergedPr], wpt_pr: u64) -> Vec<u64> {
+    let mut wpt_prs = range_prs.iter().map(|x| x.number).collect::<Vec<_>>();
+    if !wpt_prs.contains(&wpt_pr) {
+        wpt_prs.push(wpt_pr);
+    }
+    wpt_prs
+}
+
+/// Fill in the PRs for earlier landings that don't have per-PR latency, or
+/// that were recorded without their full list of PRs, newest first, fetching
+/// at most MAX_PR_BACKFILL commit ranges. Ranges that fail to fetch are
+/// retried on later runs, up to MAX_BACKFILL_ATTEMPTS times.
+fn backfill_pr_latency(github: &GitHub, sync_data: &mut SyncData, pr_latency: &mut PrLatency) {
+    let mut by_time = (0..sync_data.landings.len()).collect::<Vec<_>>();
+    by_time.sort_by_key(|idx| sync_data.landings[*idx].gecko_push_time);
+    let mut fetched = 0;
+    let mut failures = 0;
+    for pair in by_time.windows(2).rev() {
+        let (previous, landing) = (&sync_data.landings[pair[0]], &sync_data.landings[pair[1]]);
+        // Landings recorded before wpt_prs was added have an empty list, and
+        // those whose range failed to fetch have no per-PR latency
+        if pr_latency.contains(&landing.wpt_rev) && !landing.wpt_prs.is_empty() {
+            continue;
+        }
+        if pr_latency.failed_attempts(&landing.wpt_rev) >= MAX_BACKFILL_ATTEMPTS {
+            continue;
+        }
+        if fetched >= MAX_PR_BACKFILL {
+            info!("Stopped backfilling per-PR latency at {}", landing.wpt_rev);
+            break;
+        }
+        match get_prs_in_range(github, &previous.wpt_rev, &landing.wpt_rev) {
+            Ok(prs) => {
+                fetched += 1;
+                pr_latency.insert(&landing.wpt_rev, landing.gecko_push_time, &prs);
+                let landing = &mut sync_data.landings[pair[1]];
+                landing.wpt_prs = landing_prs(&prs, landing.wpt_pr);
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to get PRs between {} and {}: {}",
+                    previous.wpt_rev, landing.wpt_rev, err
+                );
+                pr_latency.record_failure(&landing.wpt_rev);
+                failures += 1;
+                if failures >= MAX_BACKFILL_FAILURES {
+                    info!(
+                        "Stopped backfilling per-PR latency after {} failures",
+                        failures
+                    );
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Number of periods included in each rolling median
+const ROLLING_PERIODS: usize = 4;
+
+/// Number of gaps between syncs to report
+const LONGEST_GAPS_COUNT: usize = 10;
+
+const SECONDS_PER_DAY: f64 = 24. * 3600.;
+
+#[derive(Debug, Clone, Copy)]
+enum Period {
+    Week,
+    Month,
+}
+
+impl Period {
+    fn start(&self, date: time::Date) -> Result<time::Date> {
+        Ok(match self {
+            Period::Week => {
+                date - time::Duration::days(date.weekday().number_days_from_monday().into())
+            }
+            Period::Month => date.replace_day(1)?,
+        })
+    }
+
+    fn next(&self, start: time::Date) -> Result<time::Date> {
+        Ok(match self {
+            Period::Week => start + time::Duration::weeks(1),
+            Period::Month => {
+                let (year, month) = match start.month() {
+                    time::Month::December => (start.year() + 1, time::Month::January),
+                    month => (start.year(), month.next()),
+                };
+                time::Date::from_calendar_date(year, month, 1)?
+            }
+        })
+    }
+}
+
+/// Latency statistics for the landings in one week or month
+#[derive(Debug, Serialize)]
+struct PeriodStats {
+    start: String,
+    landings: usize,
+    median_days: Option<f64>,
+    p90_days: Option<f64>,
+    p99_days: Option<f64>,
+    /// Median over this period and the preceding ones, see ROLLING_PERIODS
+    rolling_median_days: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncGap {
+    from_rev: String,
+    to_rev: String,
+    start: i64,
+    end: i64,
+    days: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct LatencyStats {
+    weekly: Vec<PeriodStats>,
+    monthly: Vec<PeriodStats>,
+    longest_gaps: Vec<SyncGap>,
+}
+
+/// Nearest-rank percentile of already sorted values
+fn percentile(sorted: &[f64], percent: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (percent / 100. * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values
+}
+
+fn latency_days(landing: &SyncPointWptRev) -> f64 {
+    (landing.gecko_push_time - landing.wpt_merge_time) as f64 / SECONDS_PER_DAY
+}
+
+/// Statistics for each period from the first landing to the last,
+/// including periods without any landings
+fn period_stats(landings: &[SyncPointWptRev], period: Period) -> Result<Vec<PeriodStats>> {
+    let mut by_period: BTreeMap<time::Date, Vec<f64>> = BTreeMap::new();
+    for landing in landings.iter() {
+        let date = OffsetDateTime::from_unix_timestamp(landing.gecko_push_time)?.date();
+        by_period
+            .entry(period.start(date)?)
+            .or_default()
+            .push(latency_days(landing));
+    }
+    let (first, last) = match (by_period.keys().next(), by_period.keys().last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut periods = Vec::new();
+    let mut start = first;
+    while start <= last {
+        periods.push((start, sorted(by_period.remove(&start).unwrap_or_default())));
+        start = period.next(start)?;
+    }
+
+    Ok(periods
+        .iter()
+        .enumerate()
+        .map(|(idx, (start, latencies))| {
+            let window = &periods[(idx + 1).saturating_sub(ROLLING_PERIODS)..=idx];
+            let rolling = sorted(
+                window
+                    .iter()
+                    .flat_map(|(_, latencies)| latencies.iter().copied())
+                    .collect(),
+            );
+            PeriodStats {
+                start: start.to_string(),
+                landings: latencies.len(),
+                median_days: percentile(latencies, 50.),
+                p90_days: percentile(latencies, 90.),
+                p99_days: percentile(latencies, 99.),
+                rolling_median_days: percentile(&rolling, 50.),
+            }
+        })
+        .collect())
+}
+
+/// The longest times between consecutive sync landings
+fn longest_gaps(landings: &[SyncPointWptRev]) -> Vec<SyncGap> {
+    let mut by_time = landings.iter().collect::<Vec<_>>();
+    by_time.sort_by_key(|x| x.gecko_push_time);
+    let mut gaps = by_time
+        .windows(2)
+        .map(|pair| SyncGap {
+            from_rev: pair[0].wpt_rev.clone(),
+            to_rev: pair[1].wpt_rev.clone(),
+            start: pair[0].gecko_push_time,
+            end: pair[1].gecko_push_time,
+            days: (pair[1].gecko_push_time - pair[0].gecko_push_time) as f64 / SECONDS_PER_DAY,
+        })
+        .collect::<Vec<_>>();
+    gaps.sort_by(|a, b| b.days.total_cmp(&a.days));
+    gaps.truncate(LONGEST_GAPS_COUNT);
+    gaps
+}
+
+/// Write summary statistics for the sync latency, so that consumers of
+/// the data don't need to compute them from the raw landings
+fn write_latency_stats(sync_data: &SyncData, path: &Path) -> Result<()> {
+    let stats = LatencyStats {
+        weekly: period_stats(&sync_data.landings, Period::Week)?,
+        monthly: period_stats(&sync_data.landings, Period::Month)?,
+        longest_gaps: longest_gaps(&sync_data.landings),
+    };
+    let out_f = File::create(path)?;
+    serde_json::to_writer(out_f, &stats)?;
+    Ok(())
+}
+
+/// A Gecko change to the wpt tests and the merge of its upstream PR
+#[derive(Debug, Deserialize, Serialize)]
+struct ExportLanding {
+    gecko_rev: String,
+    bug: Option<u64>,
+    wpt_pr: u64,
+    gecko_push_time: i64,
+    wpt_merge_time: i64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ExportData {
+    exports: Vec<ExportLanding>,
+}
+
+fn load_export_data(path: &Path) -> Result<ExportData> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(ExportData::default())
+    }
+}
+
+/// A merged wpt PR exported from Gecko, with the Gecko metadata wpt-sync
+/// adds to the PR description
+#[derive(Debug)]
+struct ExportedPr {
+    number: u64,
+    merged_at: i64,
+    gecko_rev: Option<String>,
+    bug: Option<u64>,
+}
+
+impl ExportedPr {
+    fn from_issue(issue: github::Issue) -> Option<ExportedPr> {
+        let merged_at = issue.pull_request?.merged_at?.unix_timestamp();
+        let body = issue.body.unwrap_or_default();
+        let capture = |re: &Regex| {
+            re.captures(&body)
+                .and_then(|captures| captures.get(1))
+                .map(|x| x.as_str().to_owned())
+        };
+        Some(ExportedPr {
+            number: issue.number,
+            merged_at,
+            gecko_rev: capture(&GECKO_COMMIT_RE).map(|x| x.to_lowercase()),
+            bug: capture(&BUGZILLA_URL_RE).and_then(|x| x.parse().ok()),
+        })
+    }
+}
+
+/// Get the exported PRs merged since the given date
+fn get_exported_prs(github: &GitHub, since: time::Date) -> Result<Vec<ExportedPr>> {
+    let query = format!(
+        "repo:{} is:pr is:merged label:{} merged:>={}",
+        WPT_REPO, EXPORT_LABEL, since
+    );
+    let mut prs = Vec::new();
+    for page in 1..=MAX_SEARCH_PAGES {
+        let results = github.search_issues(&query, SEARCH_PAGE_SIZE, page)?;
+        let page_len = results.items.len();
+        prs.extend(results.items.into_iter().filter_map(ExportedPr::from_issue));
+        if page_len < SEARCH_PAGE_SIZE {
+            break;
+        }
+    }
+    Ok(prs)
+}
+
+/// Gecko commits that could be exported upstream.
+///
+/// Downstream syncs and backouts also touch the tests, so skip those.
+fn is_export_candidate(commit: &GeckoCommit) -> bool {
+    !commit.desc.contains("[wpt PR ")
+        && !UPDATE_RE.is_match(&commit.desc)
+        && backed_out_revs(&commit.desc)
+            .map(|revs| revs.is_empty())
+            .unwrap_or(false)
+}
+
+fn commit_bug(commit: &GeckoCommit) -> Option<u64> {
+    GECKO_BUG_RE
+        .captures(&commit.desc)
+        .and_then(|captures| captures.get(1))
+        .and_then(|bug| bug.as_str().parse().ok())
+}
+
+/// Find the Gecko commit for an exported PR.
+///
+/// This uses the gecko-commit revision in the PR description, falling back
+/// to the newest commit for the PR's bug that landed before the PR merged.
+fn match_export<'a>(pr: &ExportedPr, commits: &'a [GeckoCommit]) -> Option<&'a GeckoCommit> {
+    if let Some(rev) = pr.gecko_rev.as_ref() {
+        if let Some(commit) = commits
+            .iter()
+            .find(|commit| commit.node.to_lowercase().starts_with(rev.as_str()))
+        {
+            return Some(commit);
+        }
+    }
+    let bug = pr.bug?;
+    commits
+        .iter()
+        .find(|commit| commit.push_date <= pr.merged_at && commit_bug(commit) == Some(bug))
+}
+
+/// Push time to read Gecko history back to when matching new exports.
+///
+/// PRs don't merge in the order their commits were pushed, so this goes by
+/// the earliest merge among the PRs that aren't recorded yet rather than
+/// by the exports already recorded.
+fn export_scan_start(prs: &[ExportedPr]) -> Option<i64> {
+    let lookback = time::Duration::days(EXPORT_LOOKBACK_DAYS).whole_seconds();
+    prs.iter()
+        .map(|pr| pr.merged_at)
+        .min()
+        .map(|x| x - lookback)
+}
+
+/// Add the exports whose Gecko commit is found, returning how many were added
+fn record_exports(
+    export_data: &mut ExportData,
+    prs: &[ExportedPr],
+    commits: &[GeckoCommit],
+) -> usize {
+    let mut added = 0;
+    for pr in prs.iter() {
+        match match_export(pr, commits) {
+            Some(commit) => {
+                export_data.exports.push(ExportLanding {
+                    gecko_rev: commit.node.clone(),
+                    bug: pr.bug.or_else(|| commit_bug(commit)),
+                    wpt_pr: pr.number,
+                    gecko_push_time: commit.push_date,
+                    wpt_merge_time: pr.merged_at,
+                });
+                added += 1;
+            }
+            None => debug!("No Gecko commit found for wpt PR {}", pr.number),
+        }
+    }
+    export_data.exports.sort_by_key(|x| x.wpt_merge_time);
+    added
+}
+
+/// Record the upstream export latency for PRs merged since the last run.
+///
+/// Gecko history is read back to EXPORT_LOOKBACK_DAYS before the earliest
+/// new export merged, and not at all if no new exports have merged.
+fn update_exports(
+    client: &reqwest::blocking::Client,
+    github: &GitHub,
+    data_path: &Path,
+) -> Result<()> {
+    let mut export_data = load_export_data(data_path)?;
+    let known_prs = export_data
+        .exports
+        .iter()
+        .map(|x| x.wpt_pr)
+        .collect::<HashSet<_>>();
+
+    let cutoff =
+        (OffsetDateTime::now_utc() - time::Duration::days(EXPORT_WINDOW_DAYS)).unix_timestamp();
+    let merged_since = export_data
+        .exports
+        .iter()
+        .map(|x| x.wpt_merge_time)
+        .max()
+        .unwrap_or(cutoff)
+        .max(cutoff);
+    let merged_since = OffsetDateTime::from_unix_timestamp(merged_since)?;
+
+    let prs = get_exported_prs(github, merged_since.date())?
+        .into_iter()
+        .filter(|pr| !known_prs.contains(&pr.number))
+        .collect::<Vec<_>>();
+    let pushed_since = match export_scan_start(&prs) {
+        Some(pushed_since) => OffsetDateTime::from_unix_timestamp(pushed_since)?,
+        None => {
+            info!("Found no new upstream exports");
+            return Ok(());
+        }
+    };
+
+    let commits = match env::var_os("AREWEWPTYET_GECKO_GIT") {
+        Some(repo) => get_git_commits(
+            Path::new(&repo),
+            TESTS_PATH,
+            Some(&pushed_since.format(&Rfc3339)?),
+        )?,
+        None => get_hg_commits(client, TESTS_PATH, |commit| {
+            commit.push_date <= pushed_since.unix_timestamp()
+        })?,
+    };
+    let commits = commits
+        .into_iter()
+        .filter(is_export_candidate)
+        .collect::<Vec<_>>();
+
+    let added = record_exports(&mut export_data, &prs, &commits);
+    info!("Found {} new upstream exports", added);
+
+    let out_f = File::create(data_path)?;
+    serde_json::to_writer(out_f, &export_data)?;
+    Ok(())
+}
+
+/// Hours without a new sync landing before the sync counts as stalled
+const DEFAULT_STALL_HOURS: i64 = 72;
+
+/// Number of merged wpt PRs waiting to land before the sync counts as stalled
+const DEFAULT_QUEUE_LIMIT: u64 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum StallKind {
+    NoRecentLanding,
+    PendingQueue,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct StallAlert {
+    kind: StallKind,
+    message: String,
+}
+
+/// Health of the wpt to Gecko sync.
+///
+/// This is only rewritten when the alerts or the latest landing change, so
+/// that CI doesn't commit it on every run; pending_prs is the count at
+/// that time.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SyncStatus {
+    last_landing: Option<i64>,
+    last_wpt_rev: Option<String>,
+    /// wpt PRs merged after the last landed one
+    pending_prs: Option<u64>,
+    alerts: Vec<StallAlert>,
+}
+
+impl SyncStatus {
+    fn alert_kinds(&self) -> Vec<StallKind> {
+        let mut kinds = self.alerts.iter().map(|x| x.kind).collect::<Vec<_>>();
+        kinds.sort();
+        kinds
+    }
+}
+
+fn load_sync_status(path: &Path) -> Result<SyncStatus> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(SyncStatus::default())
+    }
+}
+
+fn env_number<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("Invalid value {} for {}", value, name)),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Count the wpt PRs merged after the given time
+fn count_pending_prs(github: &GitHub, since: i64) -> Result<u64> {
+    let since = OffsetDateTime::from_unix_timestamp(since)?.format(&Rfc3339)?;
+    let query = format!("repo:{} is:pr is:merged merged:>{}", WPT_REPO, since);
+    Ok(github.search_issues(&query, 1, 1)?.total_count)
+}
+
+/// Check whether the sync has stalled.
+///
+/// AREWEWPTYET_SYNC_STALL_HOURS and AREWEWPTYET_SYNC_QUEUE_LIMIT override
+/// the default thresholds.
+fn check_sync_status(
+    github: &GitHub,
+    sync_data: &SyncData,
+    now: OffsetDateTime,
+) -> Result<SyncStatus> {
+    let stall_hours = env_number("AREWEWPTYET_SYNC_STALL_HOURS", DEFAULT_STALL_HOURS)?;
+    let queue_limit = env_number("AREWEWPTYET_SYNC_QUEUE_LIMIT", DEFAULT_QUEUE_LIMIT)?;
+
+    let mut status = SyncStatus::default();
+    let latest = match sync_data.landings.iter().max_by_key(|x| x.gecko_push_time) {
+        Some(latest) => latest,
+        None => return Ok(status),
+    };
+    status.last_landing = Some(latest.gecko_push_time);
+    status.last_wpt_rev = Some(latest.wpt_rev.clone());
+
+    let hours_since_landing = (now.unix_timestamp() - latest.gecko_push_time) / 3600;
+    if hours_since_landing >= stall_hours {
+        status.alerts.push(StallAlert {
+            kind: StallKind::NoRecentLanding,
+            message: format!(
+                "No wpt sync has landed for {} hours, the last was {}",
+                hours_since_landing, latest.wpt_rev
+            ),
+        });
+    }
+
+    let pending_prs = count_pending_prs(github, latest.wpt_merge_time)?;
+    status.pending_prs = Some(pending_prs);
+    if pending_prs >= queue_limit {
+        status.alerts.push(StallAlert {
+            kind: StallKind::PendingQueue,
+            message: format!("{} merged wpt PRs are waiting to land", pending_prs),
+        });
+    }
+    Ok(status)
+}
+
+/// Post the sync status to a webhook
+fn notify_webhook(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    status: &SyncStatus,
+) -> Result<()> {
+    let mut headers = reqwest::header::HeaderMap::new();
+    headers.insert("Content-Type", "application/json".parse()?);
+    post(client, url, Some(headers), Some(status))?;
+    Ok(())
+}
+
+/// Write the sync status if it has changed, and send it to the webhook in
+/// AREWEWPTYET_SYNC_ALERT_WEBHOOK if the set of alerts has changed since
+/// the last run.
+fn update_sync_status(
+    client: &reqwest::blocking::Client,
+    github: &GitHub,
+    sync_data: &SyncData,
+    path: &Path,
+) -> Result<()> {
+    let previous = load_sync_status(path)?;
+    let status = check_sync_status(github, sync_data, OffsetDateTime::now_utc())?;
+    for alert in status.alerts.iter() {
+        warn!("Sync stalled: {}", alert.message);
+    }
+
+    let alerts_changed = status.alert_kinds() != previous.alert_kinds();
+    if alerts_changed || status.last_wpt_rev != previous.last_wpt_rev {
+        let out_f = File::create(path)?;
+        serde_json::to_writer(out_f, &status)?;
+    }
+
+    if alerts_changed {
+        if let Ok(url) = env::var("AREWEWPTYET_SYNC_ALERT_WEBHOOK") {
+            // Failing to notify shouldn't stop the rest of the data updating
+            if let Err(err) = notify_webhook(client, &url, &status) {
+                warn!("Failed to send sync alert: {}", err);
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn run() -> Result<()> {
     let client = network::client()?;
+    let github = GitHub::from_env(&client)?;
+    // GitHub responses are kept between runs; AREWEWPTYET_GITHUB_CACHE overrides where
+    let cache_path = env::var_os("AREWEWPTYET_GITHUB_CACHE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_GITHUB_CACHE));
+    if let Err(err) = github.load_cache(&cache_path) {
+        warn!(
+            "Failed to load GitHub cache {}: {}",
+            cache_path.display(),
+            err
+        );
+    }
 
-    let sync_points_data = get_sync_commits(&client)?;
-    let sync_points = extract_sync_points(&sync_points_data)?;
+    let result = update(&client, &github);
+    // Save even after a failure, so the responses fetched so far are reused
+    if let Err(err) = github.save_cache(&cache_path) {
+        warn!(
+            "Failed to save GitHub cache {}: {}",
+            cache_path.display(),
+            err
+        );
+    }
+    result
+}
 
+fn update(client: &reqwest::blocking::Client, github: &GitHub) -> Result<()> {
     let data_path = Path::new("../docs/landings.json");
     let sync_data = load_sync_data(data_path)?;
     let mut landings = LandingData::new(sync_data);
-    let missing = landings.missing(sync_points);
+
+    // AREWEWPTYET_GECKO_GIT points at a local Firefox clone to use instead of hg
+    let sync_commits = match env::var_os("AREWEWPTYET_GECKO_GIT") {
+        Some(repo) => get_git_commits(Path::new(&repo), SYNC_PATH, None)?,
+        None => get_sync_commits(client, &landings)?,
+    };
+    let mut rejected = Vec::new();
+    let sync_points = extract_sync_points(sync_commits, &mut rejected);
+    write_rejected_commits(Path::new("../docs/rejected-sync-commits.csv"), &rejected)?;
+
+    let pr_latency_path = Path::new("../docs/pr-latency.json");
+    let mut pr_latency = PrLatency::load(pr_latency_path)?;
+
+    let missing = landings.missing(sync_points.into_iter());
     info!("Found {} missing sync points", missing.len());
     for sync_point in missing.into_iter().rev() {
-        let pr_data = get_pr_for_rev(&client, &sync_point.wpt_rev)?;
-        debug!("pr_data:\n{}", pr_data);
-        let mut prs: Vec<GitHubPr> = serde_json::from_str(&pr_data)?;
-        if prs.is_empty() {
-            info!("No PR found for commit {}", &sync_point.wpt_rev);
-            continue;
-        } else if prs.len() > 1 {
-            info!("Multiple PRs found for commit {}", &sync_point.wpt_rev);
+        let prs = github.commit_pulls(WPT_REPO, &sync_point.wpt_rev)?;
+        // None if the range couldn't be fetched; backfill_pr_latency retries it later
+        let range_prs = match landings.previous_rev(sync_point.push_date) {
+            Some(previous_rev) => {
+                match get_prs_in_range(github, previous_rev, &sync_point.wpt_rev) {
+                    Ok(prs) => Some(prs),
+                    Err(err) => {
+                        warn!(
+                            "Failed to get PRs between {} and {}: {}",
+                            previous_rev, sync_point.wpt_rev, err
+                        );
+                        None
+                    }
+                }
+            }
+            None => Some(Vec::new()),
+        };
+        let range_prs_or_empty = range_prs.as_deref().unwrap_or_default();
+        // Fall back to the PR the commit range says was merged as wpt_rev
+        let pr = merged_pr_for_rev(&prs, &sync_point.wpt_rev).or_else(|| {
+            range_prs_or_empty
+                .iter()
+                .find(|pr| pr.sha == sync_point.wpt_rev)
+                .cloned()
+        });
+        let pr = match pr {
+            Some(pr) => pr,
+            None => {
+                info!("No merged PR found for commit {}", &sync_point.wpt_rev);
+                continue;
+            }
+        };
+        match range_prs {
+            Some(ref range_prs) if !range_prs.is_empty() => {
+                pr_latency.insert(&sync_point.wpt_rev, sync_point.push_date, range_prs)
+            }
+            Some(_) => pr_latency.insert(
+                &sync_point.wpt_rev,
+                sync_point.push_date,
+                std::slice::from_ref(&pr),
+            ),
+            None => {}
         }
-        let pr = prs.remove(0);
-        landings.insert(sync_point, pr);
+        let wpt_prs = landing_prs(range_prs_or_empty, pr.number);
+        landings.insert(sync_point, &pr, wpt_prs);
     }
+    backfill_pr_latency(github, &mut landings.sync_data, &mut pr_latency);
+    pr_latency.write(pr_latency_path)?;
 
     let out_f = File::create(data_path)?;
     serde_json::to_writer(out_f, &landings.sync_data)?;
-    Ok(())
+    write_latency_stats(&landings.sync_data, Path::new("../docs/latency-stats.json"))?;
+    update_sync_status(
+        client,
+        github,
+        &landings.sync_data,
+        Path::new("../docs/sync-status.json"),
+    )?;
+
+    update_exports(client, github, Path::new("../docs/exports.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Response, TestServer};
+    use url::Url;
+
+    const BASE_REV: &str = "0000000000000000000000000000000000000000";
+
+    fn sync_points(commits: Vec<GeckoCommit>) -> (Vec<(String, i64)>, Vec<RejectedCommit>) {
+        let mut rejected = Vec::new();
+        let sync_points = extract_sync_points(commits, &mut rejected)
+            .into_iter()
+            .map(|x| (x.wpt_rev, x.push_date))
+            .collect();
+        (sync_points, rejected)
+    }
+
+    fn expected(sync_points: &[(&str, i64)]) -> Vec<(String, i64)> {
+        sync_points
+            .iter()
+            .map(|(rev, date)| (rev.to_string(), *date))
+            .collect()
+    }
+
+    #[test]
+    fn short_hash_backout() {
+        let log = r#"{"entries": [
+            {"node": "b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1",
+             "desc": "Backed out changeset a1a1a1a1a1a1 (bug 1900001) for causing wpt failures. CLOSED TREE",
+             "user": "Sheriff", "pushdate": [1700000300, 0]},
+            {"node": "a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1a1",
+             "desc": "Bug 1900001 - [wpt-sync] Update web-platform-tests to 1111111111111111111111111111111111111111, a=testonly",
+             "user": "wptsync", "pushdate": [1700000200, 0]},
+            {"node": "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+             "desc": "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly",
+             "user": "wptsync", "pushdate": [1700000100, 0]}
+        ]}"#;
+        let (sync_points, rejected) = sync_points(parse_hg_log(log).unwrap());
+        assert_eq!(sync_points, expected(&[(BASE_REV, 1700000100)]));
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn multiple_revisions_on_one_line() {
+        let log = r#"{"entries": [
+            {"node": "b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2b2",
+             "desc": "Backed out changesets a2a2a2a2a2a2 and d2d2d2d2d2d2 (bug 1900002) for bustage. CLOSED TREE",
+             "user": "Sheriff", "pushdate": [1700000400, 0]},
+            {"node": "d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2d2",
+             "desc": "Bug 1900002 - [wpt-sync] Update web-platform-tests to 2222222222222222222222222222222222222222, a=testonly",
+             "user": "wptsync", "pushdate": [1700000300, 0]},
+            {"node": "a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2a2",
+             "desc": "Bug 1900002 - [wpt-sync] Update web-platform-tests to 2020202020202020202020202020202020202020, a=testonly",
+             "user": "wptsync", "pushdate": [1700000200, 0]},
+            {"node": "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+             "desc": "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly",
+             "user": "wptsync", "pushdate": [1700000100, 0]}
+        ]}"#;
+        let (sync_points, rejected) = sync_points(parse_hg_log(log).unwrap());
+        assert_eq!(sync_points, expected(&[(BASE_REV, 1700000100)]));
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn multiple_revisions_on_separate_lines() {
+        let log = r#"{"entries": [
+            {"node": "b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3",
+             "desc": "Backed out 2 changesets (bug 1900003) for causing wpt failures. CLOSED TREE\n\nBacked out changeset d3d3d3d3d3d3 (bug 1900003)\nBacked out changeset a3a3a3a3a3a3 (bug 1900003)",
+             "user": "Sheriff", "pushdate": [1700000400, 0]},
+            {"node": "d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3d3",
+             "desc": "Bug 1900003 - [wpt-sync] Update web-platform-tests to 3333333333333333333333333333333333333333, a=testonly",
+             "user": "wptsync", "pushdate": [1700000300, 0]},
+            {"node": "a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3",
+             "desc": "Bug 1900003 - [wpt-sync] Update web-platform-tests to 3030303030303030303030303030303030303030, a=testonly",
+             "user": "wptsync", "pushdate": [1700000200, 0]},
+            {"node": "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+             "desc": "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly",
+             "user": "wptsync", "pushdate": [1700000100, 0]}
+        ]}"#;
+        let (sync_points, rejected) = sync_points(parse_hg_log(log).unwrap());
+        assert_eq!(sync_points, expected(&[(BASE_REV, 1700000100)]));
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn backout_of_backout_relands() {
+        let log = r#"{"entries": [
+            {"node": "e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4e4",
+             "desc": "Backed out changeset b4b4b4b4b4b4 (bug 1900004) as the failures were unrelated",
+             "user": "Sheriff", "pushdate": [1700000400, 0]},
+            {"node": "b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4b4",
+             "desc": "Backed out changeset a4a4a4a4a4a4 (bug 1900004) for causing wpt failures. CLOSED TREE",
+             "user": "Sheriff", "pushdate": [1700000300, 0]},
+            {"node": "a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4a4",
+             "desc": "Bug 1900004 - [wpt-sync] Update web-platform-tests to 4444444444444444444444444444444444444444, a=testonly",
+             "user": "wptsync", "pushdate": [1700000200, 0]},
+            {"node": "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+             "desc": "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly",
+             "user": "wptsync", "pushdate": [1700000100, 0]}
+        ]}"#;
+        let (sync_points, rejected) = sync_points(parse_hg_log(log).unwrap());
+        // The sync point counts as landing when it was relanded
+        assert_eq!(
+            sync_points,
+            expected(&[
+                ("4444444444444444444444444444444444444444", 1700000400),
+                (BASE_REV, 1700000100),
+            ])
+        );
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn git_revert() {
+        let log = concat!(
+            "f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5f5\x001700000300\x00",
+            "Revert \"Bug 1900005 - [wpt-sync] Update web-platform-tests to 5555555555555555555555555555555555555555, a=testonly\"\n\n",
+            "This reverts commit a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5.\n\x1e\n",
+            "a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5\x001700000200\x00",
+            "Bug 1900005 - [wpt-sync] Update web-platform-tests to 5555555555555555555555555555555555555555, a=testonly\n\x1e\n",
+            "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0\x001700000100\x00",
+            "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly\n\x1e\n",
+        );
+        let (sync_points, rejected) = sync_points(parse_git_log(log).unwrap());
+        assert_eq!(sync_points, expected(&[(BASE_REV, 1700000100)]));
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn short_revision_rejected() {
+        let log = r#"{"entries": [
+            {"node": "b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6",
+             "desc": "Backed out changeset a6a6a (bug 1900006) for causing wpt failures",
+             "user": "Sheriff", "pushdate": [1700000300, 0]},
+            {"node": "a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6a6",
+             "desc": "Bug 1900006 - [wpt-sync] Update web-platform-tests to 6666666666666666666666666666666666666666, a=testonly",
+             "user": "wptsync", "pushdate": [1700000200, 0]},
+            {"node": "c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0",
+             "desc": "Bug 1900000 - [wpt-sync] Update web-platform-tests to 0000000000000000000000000000000000000000, a=testonly",
+             "user": "wptsync", "pushdate": [1700000100, 0]}
+        ]}"#;
+        let (sync_points, rejected) = sync_points(parse_hg_log(log).unwrap());
+        // The backout can't be applied, so the sync point still counts
+        assert_eq!(
+            sync_points,
+            expected(&[
+                ("6666666666666666666666666666666666666666", 1700000200),
+                (BASE_REV, 1700000100),
+            ])
+        );
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].node, "b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6b6");
+        assert!(matches!(
+            &rejected[0].error,
+            GeckoCommitError::ShortRevision(rev) if rev == "a6a6a"
+        ));
+    }
+
+    const STALL_HOURS_VAR: &str = "AREWEWPTYET_SYNC_STALL_HOURS";
+    const QUEUE_LIMIT_VAR: &str = "AREWEWPTYET_SYNC_QUEUE_LIMIT";
+    const WEBHOOK_VAR: &str = "AREWEWPTYET_SYNC_ALERT_WEBHOOK";
+
+    /// A GitHub stand-in that reports the given number of merged PRs
+    fn pending_prs_server(pending_prs: u64) -> TestServer {
+        TestServer::start(move |_| {
+            Response::json(&format!(
+                r#"{{"total_count": {}, "items": []}}"#,
+                pending_prs
+            ))
+        })
+    }
+
+    fn sync_data(hours_ago: i64) -> SyncData {
+        let push_time = OffsetDateTime::now_utc().unix_timestamp() - hours_ago * 3600;
+        SyncData {
+            landings: vec![SyncPointWptRev {
+                wpt_rev: "1111111111111111111111111111111111111111".into(),
+                wpt_pr: 1,
+                wpt_merge_time: push_time - 3600,
+                gecko_push_time: push_time,
+                wpt_prs: vec![1],
+            }],
+        }
+    }
+
+    fn alert_kinds(
+        github_server: &TestServer,
+        sync_data: &SyncData,
+        stall_hours: Option<&str>,
+        queue_limit: Option<&str>,
+    ) -> Vec<StallKind> {
+        let client = test_server::client();
+        let github = GitHub::new(&client, Url::parse(github_server.url()).unwrap(), None);
+        let _env = test_server::lock_env();
+        for (name, value) in [
+            (STALL_HOURS_VAR, stall_hours),
+            (QUEUE_LIMIT_VAR, queue_limit),
+        ] {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        check_sync_status(&github, sync_data, OffsetDateTime::now_utc())
+            .unwrap()
+            .alert_kinds()
+    }
+
+    #[test]
+    fn default_stall_thresholds() {
+        let server = pending_prs_server(DEFAULT_QUEUE_LIMIT - 1);
+        assert_eq!(alert_kinds(&server, &sync_data(71), None, None), []);
+        assert_eq!(
+            alert_kinds(&server, &sync_data(72), None, None),
+            [StallKind::NoRecentLanding]
+        );
+
+        let server = pending_prs_server(DEFAULT_QUEUE_LIMIT);
+        assert_eq!(
+            alert_kinds(&server, &sync_data(1), None, None),
+            [StallKind::PendingQueue]
+        );
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/search/issues");
+        let query = request.query("q").unwrap();
+        assert!(query.starts_with("repo:web-platform-tests/wpt is:pr is:merged merged:>"));
+    }
+
+    #[test]
+    fn configured_stall_thresholds() {
+        let server = pending_prs_server(20);
+        let sync_data = sync_data(10);
+        assert_eq!(alert_kinds(&server, &sync_data, None, None), []);
+        assert_eq!(
+            alert_kinds(&server, &sync_data, Some("10"), Some("20")),
+            [StallKind::NoRecentLanding, StallKind::PendingQueue]
+        );
+        assert_eq!(alert_kinds(&server, &sync_data, Some("11"), Some("21")), []);
+    }
+
+    #[test]
+    fn webhook_sent_when_alerts_change() {
+        let github_server = pending_prs_server(0);
+        let webhook = TestServer::start(|_| Response::new(200, ""));
+        let client = test_server::client();
+        let github = GitHub::new(&client, Url::parse(github_server.url()).unwrap(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sync-status.json");
+
+        let _env = test_server::lock_env();
+        env::remove_var(STALL_HOURS_VAR);
+        env::remove_var(QUEUE_LIMIT_VAR);
+        env::set_var(WEBHOOK_VAR, webhook.url());
+        let stalled = sync_data(100);
+        let update = |sync_data: &SyncData| {
+            update_sync_status(&client, &github, sync_data, &path).unwrap();
+            webhook.requests().len()
+        };
+
+        assert_eq!(update(&stalled), 1);
+        let request = &webhook.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+        let sent: SyncStatus = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(sent.alert_kinds(), [StallKind::NoRecentLanding]);
+
+        // An unchanged stall isn't reported or rewritten again
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(update(&stalled), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), written);
+        let written: SyncStatus = serde_json::from_str(&written).unwrap();
+        assert_eq!(written.alert_kinds(), [StallKind::NoRecentLanding]);
+        assert_eq!(
+            written.last_wpt_rev.as_deref(),
+            Some("1111111111111111111111111111111111111111")
+        );
+        assert_eq!(written.pending_prs, Some(0));
+
+        // Recovering clears the alert
+        assert_eq!(update(&sync_data(1)), 2);
+        let sent: SyncStatus = serde_json::from_str(&webhook.requests()[1].body).unwrap();
+        assert!(sent.alerts.is_empty());
+        env::remove_var(WEBHOOK_VAR);
+    }
+
+    fn gecko_commit(node: &str, bug: u64, push_date: i64) -> GeckoCommit {
+        GeckoCommit {
+            node: node.into(),
+            desc: format!("Bug {} - Fix the tests r=reviewer", bug),
+            push_date,
+        }
+    }
+
+    fn exported_pr(number: u64, gecko_rev: &str, merged_at: i64) -> ExportedPr {
+        ExportedPr {
+            number,
+            merged_at,
+            gecko_rev: Some(gecko_rev[..12].into()),
+            bug: None,
+        }
+    }
+
+    /// Gecko commits pushed since a time, as read back from the history
+    fn commits_since(commits: Vec<GeckoCommit>, since: i64) -> Vec<GeckoCommit> {
+        commits
+            .into_iter()
+            .filter(|commit| commit.push_date >= since)
+            .collect()
+    }
+
+    #[test]
+    fn exports_merged_out_of_push_order() {
+        let hour = 3600;
+        let pushed_a = 1700000000;
+        let pushed_b = pushed_a + hour;
+        let a = "a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3a3";
+        let b = "b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3b3";
+        let history = || vec![gecko_commit(b, 1, pushed_b), gecko_commit(a, 1, pushed_a)];
+        let mut export_data = ExportData::default();
+
+        // The later commit's PR merges first
+        let prs = [exported_pr(2, b, pushed_b + hour)];
+        let since = export_scan_start(&prs).unwrap();
+        assert_eq!(
+            record_exports(&mut export_data, &prs, &commits_since(history(), since)),
+            1
+        );
+
+        // The earlier commit's PR merges later, and is still found although
+        // it was pushed before the recorded export
+        let prs = [exported_pr(1, a, pushed_b + 24 * hour)];
+        let since = export_scan_start(&prs).unwrap();
+        assert!(since <= pushed_a);
+        assert_eq!(
+            record_exports(&mut export_data, &prs, &commits_since(history(), since)),
+            1
+        );
+
+        let exports = export_data
+            .exports
+            .iter()
+            .map(|x| (x.wpt_pr, x.gecko_rev.as_str(), x.gecko_push_time))
+            .collect::<Vec<_>>();
+        assert_eq!(exports, [(2, b, pushed_b), (1, a, pushed_a)]);
+    }
+
+    #[test]
+    fn no_scan_without_new_exports() {
+        assert_eq!(export_scan_start(&[]), None);
+    }
+
+    fn landing(
+        wpt_rev: &str,
+        wpt_pr: u64,
+        gecko_push_time: i64,
+        wpt_prs: &[u64],
+    ) -> SyncPointWptRev {
+        SyncPointWptRev {
+            wpt_rev: wpt_rev.into(),
+            wpt_pr,
+            wpt_merge_time: gecko_push_time - 3600,
+            gecko_push_time,
+            wpt_prs: wpt_prs.to_vec(),
+        }
+    }
+
+    /// A GitHub stand-in that lists squash merges of the given PRs for
+    /// each compared range, and fails for unknown ranges
+    fn compare_server(ranges: &'static [(&'static str, &'static [u64])]) -> TestServer {
+        TestServer::start(move |request| {
+            let range = request
+                .path
+                .trim_start_matches("/repos/web-platform-tests/wpt/compare/");
+            match ranges.iter().find(|(name, _)| *name == range) {
+                Some((_, prs)) => {
+                    let commits = prs
+                        .iter()
+                        .map(|pr| {
+                            format!(
+                                r#"{{"sha": "sha{}", "commit": {{"message": "Fix (#{})", "committer": {{"date": "2023-11-14T22:00:00Z"}}}}}}"#,
+                                pr, pr
+                            )
+                        })
+                        .collect::<Vec<_>>();
+                    Response::json(&format!(r#"{{"commits": [{}]}}"#, commits.join(",")))
+                }
+                None => Response::new(404, ""),
+            }
+        })
+    }
+
+    #[test]
+    fn backfill_fills_landing_prs() {
+        let server = compare_server(&[("r0...r1", &[10, 11]), ("r1...r2", &[12, 13])]);
+        let client = test_server::client();
+        let github = GitHub::new(&client, Url::parse(server.url()).unwrap(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let mut pr_latency = PrLatency::load(&dir.path().join("pr-latency.json")).unwrap();
+        // r1 was recorded before landings listed their PRs, and r2's range
+        // failed to fetch when it landed
+        pr_latency.insert("r1", 200, &[]);
+        let mut sync_data = SyncData {
+            landings: vec![
+                landing("r2", 13, 300, &[13]),
+                landing("r0", 9, 100, &[9]),
+                landing("r1", 11, 200, &[]),
+            ],
+        };
+        backfill_pr_latency(&github, &mut sync_data, &mut pr_latency);
+
+        let wpt_prs = sync_data
+            .landings
+            .iter()
+            .map(|x| (x.wpt_rev.as_str(), x.wpt_prs.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            wpt_prs,
+            [("r2", vec![12, 13]), ("r0", vec![9]), ("r1", vec![10, 11])]
+        );
+        assert!(pr_latency.contains("r2"));
+        assert_eq!(
+            pr_latency
+                .data
+                .prs
+                .iter()
+                .map(|x| (x.wpt_pr, x.wpt_rev.as_str()))
+                .collect::<Vec<_>>(),
+            [(12, "r2"), (13, "r2")]
+        );
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[test]
+    fn backfill_gives_up_on_failing_ranges() {
+        // r1...r2 can't be compared, e.g. because r1 was force-pushed away
+        let server = compare_server(&[("r0...r1", &[10, 11])]);
+        let client = test_server::client();
+        let github = GitHub::new(&client, Url::parse(server.url()).unwrap(), None);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pr-latency.json");
+        let mut sync_data = SyncData {
+            landings: vec![
+                landing("r0", 9, 100, &[9]),
+                landing("r1", 11, 200, &[11]),
+                landing("r2", 13, 300, &[13]),
+            ],
+        };
+
+        for _ in 0..MAX_BACKFILL_ATTEMPTS + 1 {
+            let mut pr_latency = PrLatency::load(&path).unwrap();
+            backfill_pr_latency(&github, &mut sync_data, &mut pr_latency);
+            // The older landing isn't held up by the failing one
+            assert!(pr_latency.contains("r1"));
+            assert!(!pr_latency.contains("r2"));
+            pr_latency.write(&path).unwrap();
+        }
+
+        let pr_latency = PrLatency::load(&path).unwrap();
+        assert_eq!(pr_latency.failed_attempts("r2"), MAX_BACKFILL_ATTEMPTS);
+        let paths = server
+            .requests()
+            .into_iter()
+            .map(|x| x.path)
+            .collect::<Vec<_>>();
+        let failed_path = "/repos/web-platform-tests/wpt/compare/r1...r2".to_owned();
+        assert_eq!(
+            paths.iter().filter(|x| **x == failed_path).count(),
+            MAX_BACKFILL_ATTEMPTS as usize
+        );
+        assert_eq!(paths.len(), MAX_BACKFILL_ATTEMPTS as usize + 1);
+    }
 }
@@ -1,11 +1,28 @@
+mod bugzilla;
 mod failures;
+mod github;
 mod interop;
 mod latency;
 mod network;
+mod team;
+#[cfg(test)]
+mod test_server;
+mod triage;
+mod wpt_data;
 
+use anyhow::Result;
 use log::error;
 use std::process;
 
+/// Generate the outputs that use wpt.fyi results and metadata, sharing the
+/// downloaded data between them
+fn wpt_outputs() -> Vec<Result<()>> {
+    match wpt_data::WptData::fetch() {
+        Ok(wpt) => vec![interop::run(&wpt), triage::run(&wpt), team::run(&wpt)],
+        Err(err) => vec![Err(err)],
+    }
+}
+
 fn main() {
     let mut log_builder = env_logger::Builder::new();
     log_builder
@@ -13,7 +30,8 @@ fn main() {
         .parse_default_env()
         .init();
 
-    let results = [failures::run(), latency::run(), interop::run()];
+    let mut results = vec![failures::run(), latency::run()];
+    results.extend(wpt_outputs());
 
     let errors = results
         .iter()
@@ -0,0 +1,220 @@
+use crate::interop::{
+    get_focus_area_tests, write_focus_area, write_next_release_fixes, FocusAreaTests,
+    InteropContext, TestFailures,
+};
+use crate::wpt_data::WptData;
+use anyhow::{anyhow, Result};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::path::Path;
+use wptfyi::bug::BugLinkParser;
+use wptfyi::interop::BrowserChannel;
+use wptfyi::result;
+
+/// A focus area tracked by the team rather than the Interop project.
+///
+/// Tests are included if they have any of the labels, match any of the
+/// path globs, or are listed explicitly.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct TeamFocusArea {
+    labels: Vec<String>,
+    paths: Vec<String>,
+    tests: Vec<String>,
+}
+
+impl TeamFocusArea {
+    fn tests(&self) -> FocusAreaTests<'_> {
+        FocusAreaTests {
+            labels: self.labels.iter().map(|x| x.as_ref()).collect(),
+            paths: self.paths.iter().map(|x| x.as_ref()).collect(),
+            tests: self.tests.iter().map(|x| x.as_ref()).collect(),
+        }
+    }
+}
+
+/// Load the team focus areas, keyed by name.
+///
+/// See team_focus_areas.example.json for the format. Path globs must start
+/// with a directory, since that's what's used to search wpt.fyi.
+fn load_team_focus_areas(path: &Path) -> Result<BTreeMap<String, TeamFocusArea>> {
+    if let Ok(f) = File::open(path) {
+        Ok(serde_json::from_reader(f)?)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Directory holding the team focus area data for a channel.
+///
+/// This is laid out like the interop year directories, with experimental
+/// desktop data at the top level.
+fn team_dir(channel: BrowserChannel) -> String {
+    match channel {
+        BrowserChannel::Experimental => "../docs/team".into(),
+        _ => format!("../docs/team/{}", channel),
+    }
+}
+
+/// Score for each browser in a focus area, in the channel's browser order.
+///
+/// As for interop scores, each test scores the fraction of its subtests
+/// that pass and the area score is the mean test score.
+fn focus_area_scores(channel: BrowserChannel, data: &result::SearchData) -> Result<Vec<f64>> {
+    channel
+        .browsers()
+        .iter()
+        .map(|browser| {
+            let idx = data
+                .runs
+                .iter()
+                .position(|x| x.browser_name == *browser)
+                .ok_or_else(|| anyhow!("Didn't get {} results", browser))?;
+            if data.results.is_empty() {
+                return Ok(0.);
+            }
+            let total: f64 = data
+                .results
+                .iter()
+                .filter_map(|result| result.legacy_status.get(idx))
+                .filter(|status| status.total > 0)
+                .map(|status| status.passes as f64 / status.total as f64)
+                .sum();
+            Ok(100. * total / data.results.len() as f64)
+        })
+        .collect()
+}
+
+/// Add today's scores to the score history for a focus area.
+///
+/// There is one row per day; running again on the same day replaces that
+/// day's row.
+fn update_score_history(
+    dir: &str,
+    name: &str,
+    channel: BrowserChannel,
+    today: time::Date,
+    scores: &[f64],
+) -> Result<()> {
+    let path = format!("{}/scores/{}.csv", dir, name);
+    let data_path = Path::new(&path);
+    let today = today.to_string();
+
+    let mut rows = Vec::new();
+    if let Ok(f) = File::open(data_path) {
+        let mut reader = csv::Reader::from_reader(f);
+        for record in reader.records() {
+            let record = record?;
+            if record.get(0) != Some(today.as_str()) {
+                rows.push(record.iter().map(String::from).collect::<Vec<_>>());
+            }
+        }
+    }
+    let mut row = Vec::with_capacity(scores.len() + 1);
+    row.push(today);
+    row.extend(scores.iter().map(|score| format!("{:.2}", score)));
+    rows.push(row);
+
+    if let Some(dir_name) = data_path.parent() {
+        fs::create_dir_all(dir_name)?;
+    }
+    let out_f = File::create(data_path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+
+    let browsers = channel.browsers();
+    let mut headers = Vec::with_capacity(browsers.len() + 1);
+    headers.push("date");
+    headers.extend_from_slice(browsers);
+    writer.write_record(headers)?;
+    for row in rows.iter() {
+        writer.write_record(row)?;
+    }
+    Ok(())
+}
+
+pub fn run(wpt: &WptData) -> Result<()> {
+    let areas = load_team_focus_areas(Path::new("../docs/team_focus_areas.json"))?;
+    if areas.is_empty() {
+        debug!("No team focus areas configured");
+        return Ok(());
+    }
+
+    let client = &wpt.client;
+    let fyi = &wpt.fyi;
+    let metadata = &wpt.metadata;
+    let bug_links = BugLinkParser::default();
+    let subtest_detail = env::var_os("AREWEWPTYET_SUBTEST_DETAIL").is_some();
+    let today = time::OffsetDateTime::now_utc().date();
+
+    let mut channel_failures: BTreeMap<BrowserChannel, BTreeMap<&str, TestFailures>> =
+        BTreeMap::new();
+    for channel in [BrowserChannel::Experimental, BrowserChannel::Stable] {
+        info!("Processing team focus areas {}", channel);
+        let runs = match wpt.channel_runs(channel) {
+            Ok(runs) => runs,
+            // As for the Interop outputs, missing stable runs shouldn't stop
+            // the experimental outputs being written
+            Err(err) if channel != BrowserChannel::Experimental => {
+                warn!("Skipping team focus areas {}: {:?}", channel, err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let run_ids = runs.iter().map(|x| x.id).collect::<Vec<_>>();
+        let ctx = InteropContext {
+            fyi,
+            client,
+            channel,
+            dir: team_dir(channel),
+            runs: &runs,
+            metadata,
+            bug_links: &bug_links,
+            subtest_detail,
+            focus_area_count: 0,
+        };
+        let failures = channel_failures.entry(channel).or_default();
+        for (name, area) in areas.iter() {
+            let tests = area.tests();
+            if tests.is_empty() {
+                warn!("Team focus area {} doesn't select any tests", name);
+                continue;
+            }
+            if let Err(err) = tests.check_paths() {
+                warn!("Skipping team focus area {}: {}", name, err);
+                continue;
+            }
+            failures.insert(name, write_focus_area(&ctx, name, &tests)?);
+
+            let mut all_tests = get_focus_area_tests(fyi, client, channel, &run_ids, &tests)?;
+            tests.retain_matching(&mut all_tests, metadata);
+            let scores = focus_area_scores(channel, &all_tests)?;
+            update_score_history(&ctx.dir, name, channel, today, &scores)?;
+        }
+    }
+
+    let (experimental_failures, stable_failures) = match (
+        channel_failures.get(&BrowserChannel::Experimental),
+        channel_failures.get(&BrowserChannel::Stable),
+    ) {
+        (Some(experimental_failures), Some(stable_failures)) => {
+            (experimental_failures, stable_failures)
+        }
+        _ => return Ok(()),
+    };
+    for (name, stable_failures) in stable_failures.iter() {
+        if let Some(experimental_failures) = experimental_failures.get(name) {
+            write_next_release_fixes(
+                &team_dir(BrowserChannel::Stable),
+                name,
+                stable_failures,
+                experimental_failures,
+            )?;
+        }
+    }
+    Ok(())
+}
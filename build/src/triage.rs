@@ -0,0 +1,526 @@
+use crate::bugzilla::{Bug, Bugzilla};
+use crate::network::{get, post};
+use crate::wpt_data::WptData;
+use anyhow::{anyhow, Result};
+use log::info;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::path::Path;
+use wptfyi::bug::{BugLinkParser, BugRef, Tracker};
+use wptfyi::interop::BrowserChannel;
+use wptfyi::metadata::MetadataEntry;
+use wptfyi::result::{self, LegacyStatus};
+use wptfyi::search::{Clause, LinkClause, OrClause, Query};
+use wptfyi::{metadata, search, Wptfyi};
+
+/// Get the results of all the tests with links matching any of the
+/// patterns
+fn get_linked_tests(
+    wptfyi: &Wptfyi,
+    client: &reqwest::blocking::Client,
+    channel: BrowserChannel,
+    run_ids: &[i64],
+    patterns: &[&str],
+) -> Result<result::SearchData> {
+    let mut search = wptfyi.search();
+    for product in channel.browsers().iter() {
+        search.add_product(product, channel.run_channel())
+    }
+    let link_parts = patterns
+        .iter()
+        .map(|pattern| {
+            Clause::Link(LinkClause {
+                link: (*pattern).to_owned(),
+            })
+        })
+        .collect();
+    search.set_query(
+        run_ids,
+        Query {
+            query: Clause::Or(OrClause { or: link_parts }),
+        },
+    );
+    search.add_label("master");
+    Ok(search::parse(&post(
+        client,
+        &String::from(search.url()),
+        None,
+        search.body(),
+    )?)?)
+}
+
+/// Status of each test in some search results, in the order of `browsers`
+type BrowserStatuses = BTreeMap<String, Vec<Option<LegacyStatus>>>;
+
+fn browser_statuses(results: &result::SearchData, browsers: &[&str]) -> Result<BrowserStatuses> {
+    let run_indices = browsers
+        .iter()
+        .map(|browser| {
+            results
+                .runs
+                .iter()
+                .position(|x| x.browser_name == *browser)
+                .ok_or_else(|| anyhow!("Didn't get {} results", browser))
+        })
+        .collect::<Result<Vec<usize>>>()?;
+    Ok(results
+        .results
+        .iter()
+        .map(|result| {
+            (
+                result.test.clone(),
+                run_indices
+                    .iter()
+                    .map(|idx| result.legacy_status.get(*idx).cloned())
+                    .collect(),
+            )
+        })
+        .collect())
+}
+
+fn fx_statuses(statuses: &BrowserStatuses) -> BTreeMap<String, LegacyStatus> {
+    statuses
+        .iter()
+        .filter_map(|(test, statuses)| {
+            statuses
+                .first()
+                .cloned()
+                .flatten()
+                .map(|status| (test.clone(), status))
+        })
+        .collect()
+}
+
+fn is_interop_label(label: &str) -> bool {
+    label.starts_with("interop-")
+}
+
+/// Interop focus area labels applied to each test in the metadata
+fn test_focus_areas(metadata: &BTreeMap<String, Vec<MetadataEntry>>) -> BTreeMap<&str, Vec<&str>> {
+    metadata::test_labels(metadata)
+        .into_iter()
+        .map(|(test, labels)| {
+            (
+                test,
+                labels.into_iter().filter(|x| is_interop_label(x)).collect(),
+            )
+        })
+        .collect()
+}
+
+fn status_column(status: Option<&LegacyStatus>) -> String {
+    status
+        .map(|x| format!("{}/{}", x.passes, x.total))
+        .unwrap_or_default()
+}
+
+/// Write each linked bug along with the tests and subtests it covers,
+/// their current status in each browser and their interop focus areas.
+fn write_bug_index(
+    path: &Path,
+    metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+    bug_links: &BugLinkParser,
+    statuses: &BrowserStatuses,
+) -> Result<()> {
+    let focus_areas = test_focus_areas(metadata);
+    let mut by_bug: BTreeMap<BugRef, Vec<(&str, &MetadataEntry)>> = BTreeMap::new();
+    for (test, entries) in metadata.iter() {
+        for entry in entries.iter() {
+            if let Some(bug) = entry.bug(bug_links) {
+                by_bug.entry(bug).or_default().push((test, entry));
+            }
+        }
+    }
+
+    let out_f = File::create(path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record([
+        "Bug",
+        "Product",
+        "Test",
+        "Subtests",
+        "Firefox",
+        "Chrome",
+        "Safari",
+        "Focus Areas",
+    ])?;
+    for (bug, tests) in by_bug.iter() {
+        for (test, entry) in tests.iter() {
+            let subtests = entry
+                .results
+                .iter()
+                .filter_map(|x| x.subtest.as_deref())
+                .collect::<Vec<&str>>()
+                .join(";");
+            let test_statuses = statuses.get(*test);
+            let mut record = vec![
+                bug.to_string(),
+                entry.product.clone(),
+                (*test).to_owned(),
+                subtests,
+            ];
+            for idx in 0..3 {
+                record.push(status_column(
+                    test_statuses
+                        .and_then(|x| x.get(idx))
+                        .and_then(|x| x.as_ref()),
+                ));
+            }
+            record.push(
+                focus_areas
+                    .get(*test)
+                    .map(|x| x.join(" "))
+                    .unwrap_or_default(),
+            );
+            writer.write_record(&record)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum StaleCategory {
+    /// The linked bug is fixed but the test still fails
+    FixedButFailing,
+    /// The test passes in Firefox but still has a bug linked
+    PassingButLinked,
+    /// The test isn't in the latest runs for any browser
+    TestNoLongerExists,
+}
+
+impl StaleCategory {
+    fn name(&self) -> &'static str {
+        match self {
+            StaleCategory::FixedButFailing => "fixed-but-failing",
+            StaleCategory::PassingButLinked => "passing-but-linked",
+            StaleCategory::TestNoLongerExists => "test-no-longer-exists",
+        }
+    }
+}
+
+struct StaleLink<'a> {
+    category: StaleCategory,
+    test: &'a str,
+    entry: &'a MetadataEntry,
+    bug: Option<&'a Bug>,
+    fx_status: Option<&'a LegacyStatus>,
+}
+
+/// Get every test in any of the runs, from the run summaries
+fn get_run_tests(
+    client: &reqwest::blocking::Client,
+    runs: &[result::Run],
+) -> Result<BTreeSet<String>> {
+    let mut tests = BTreeSet::new();
+    for run in runs.iter() {
+        let summary = get(client, &run.results_url, None)?;
+        tests.extend(result::parse_summary_tests(&summary)?);
+    }
+    Ok(tests)
+}
+
+/// Find Firefox metadata links that no longer reflect the state of the test.
+///
+/// `run_tests` is every test in the latest runs, so that tests without a
+/// Firefox result aren't reported as removed.
+fn stale_links<'a>(
+    metadata: &'a BTreeMap<String, Vec<MetadataEntry>>,
+    bug_links: &BugLinkParser,
+    run_tests: &BTreeSet<String>,
+    fx_statuses: &'a BTreeMap<String, LegacyStatus>,
+    bugs: &'a BTreeMap<u64, Bug>,
+) -> Vec<StaleLink<'a>> {
+    let mut rv = Vec::new();
+    for (test, entries) in metadata.iter() {
+        // Wildcard entries apply to a whole directory rather than a test
+        if test.ends_with('*') {
+            continue;
+        }
+        for entry in entries.iter().filter(|x| x.product == "firefox") {
+            let bug_ref = match entry.bug(bug_links) {
+                Some(bug_ref) => bug_ref,
+                None => continue,
+            };
+            let bug = match bug_ref.tracker {
+                Tracker::Bugzilla => bugs.get(&bug_ref.id),
+                _ => None,
+            };
+            let fx_status = fx_statuses.get(test);
+            let category = match fx_status {
+                _ if !run_tests.contains(test) => StaleCategory::TestNoLongerExists,
+                Some(status) if status.total > 0 && status.passes == status.total => {
+                    StaleCategory::PassingButLinked
+                }
+                Some(_) if bug.map(|x| x.is_fixed()).unwrap_or(false) => {
+                    StaleCategory::FixedButFailing
+                }
+                _ => continue,
+            };
+            rv.push(StaleLink {
+                category,
+                test,
+                entry,
+                bug,
+                fx_status,
+            });
+        }
+    }
+    rv.sort_by(|a, b| (a.category, a.test).cmp(&(b.category, b.test)));
+    rv
+}
+
+fn write_stale_links(path: &Path, stale: &[StaleLink]) -> Result<()> {
+    let out_f = File::create(path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record([
+        "Category",
+        "Test",
+        "Subtests",
+        "Link",
+        "Bug Status",
+        "Bug Resolution",
+        "Firefox Passes",
+        "Firefox Total",
+    ])?;
+    for link in stale.iter() {
+        let subtests = link
+            .entry
+            .results
+            .iter()
+            .filter_map(|x| x.subtest.as_deref())
+            .collect::<Vec<&str>>()
+            .join(";");
+        writer.write_record([
+            link.category.name(),
+            link.test,
+            &subtests,
+            &link.entry.url,
+            link.bug.map(|x| x.status.as_ref()).unwrap_or_default(),
+            link.bug.map(|x| x.resolution.as_ref()).unwrap_or_default(),
+            &link
+                .fx_status
+                .map(|x| x.passes.to_string())
+                .unwrap_or_default(),
+            &link
+                .fx_status
+                .map(|x| x.total.to_string())
+                .unwrap_or_default(),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Bugzilla bugs linked from Firefox metadata
+fn linked_bugzilla_ids(
+    metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+    bug_links: &BugLinkParser,
+) -> Vec<u64> {
+    metadata
+        .values()
+        .flatten()
+        .filter(|x| x.product == "firefox")
+        .filter_map(|x| x.bug(bug_links))
+        .filter(|x: &BugRef| x.tracker == Tracker::Bugzilla)
+        .map(|x| x.id)
+        .collect::<BTreeSet<u64>>()
+        .into_iter()
+        .collect()
+}
+
+/// Triage state of the metadata for a single product
+#[derive(Debug, Default)]
+struct ProductTriage<'a> {
+    linked_tests: BTreeSet<&'a str>,
+    bugs: BTreeSet<BugRef>,
+    scoped_links: usize,
+    unrecognised_links: usize,
+    linked_interop_tests: BTreeSet<&'a str>,
+}
+
+/// Write a summary of the triage links for each product, along with how
+/// many of the tests with interop labels have been triaged.
+fn write_triage_summary(
+    path: &Path,
+    products: &[String],
+    metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+    bug_links: &BugLinkParser,
+) -> Result<()> {
+    let labels = metadata::test_labels(metadata);
+    let interop_tests = labels
+        .iter()
+        .filter(|(_, labels)| labels.iter().any(|x| is_interop_label(x)))
+        .map(|(test, _)| *test)
+        .collect::<BTreeSet<&str>>();
+
+    let mut by_product: BTreeMap<&str, ProductTriage> = products
+        .iter()
+        .map(|x| (x.as_ref(), ProductTriage::default()))
+        .collect();
+    for (test, entries) in metadata.iter() {
+        for entry in entries.iter().filter(|x| x.is_link()) {
+            let triage = match by_product.get_mut(entry.product.as_str()) {
+                Some(triage) => triage,
+                None => continue,
+            };
+            triage.linked_tests.insert(test);
+            if interop_tests.contains(test.as_str()) {
+                triage.linked_interop_tests.insert(test);
+            }
+            if entry.is_scoped() {
+                triage.scoped_links += 1;
+            }
+            match entry.bug(bug_links) {
+                Some(bug) => {
+                    triage.bugs.insert(bug);
+                }
+                None => triage.unrecognised_links += 1,
+            }
+        }
+    }
+
+    let out_f = File::create(path)?;
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(out_f);
+    writer.write_record([
+        "Product",
+        "Linked Tests",
+        "Bugs",
+        "Scoped Links",
+        "Unrecognised Links",
+        "Interop Tests",
+        "Linked Interop Tests",
+    ])?;
+    for (product, triage) in by_product.iter() {
+        writer.write_record([
+            *product,
+            &triage.linked_tests.len().to_string(),
+            &triage.bugs.len().to_string(),
+            &triage.scoped_links.to_string(),
+            &triage.unrecognised_links.to_string(),
+            &interop_tests.len().to_string(),
+            &triage.linked_interop_tests.len().to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+pub fn run(wpt: &WptData) -> Result<()> {
+    let client = &wpt.client;
+    let metadata = &wpt.metadata;
+    let bugzilla = Bugzilla::from_env(client)?;
+    let bug_links = BugLinkParser::default();
+    let channel = BrowserChannel::Experimental;
+
+    let runs = wpt.channel_runs(channel)?;
+    let run_ids = runs.iter().map(|x| x.id).collect::<Vec<i64>>();
+
+    let linked_tests = get_linked_tests(
+        &wpt.fyi,
+        client,
+        channel,
+        &run_ids,
+        &bug_links.link_patterns(),
+    )?;
+    let statuses = browser_statuses(&linked_tests, channel.browsers())?;
+    let fx_statuses = fx_statuses(&statuses);
+    let run_tests = get_run_tests(client, &runs)?;
+
+    let bug_ids = linked_bugzilla_ids(metadata, &bug_links);
+    info!("Getting status of {} linked bugs", bug_ids.len());
+    let bugs = bugzilla
+        .bugs_by_id(&bug_ids, &["id", "status", "resolution"])?
+        .into_iter()
+        .map(|x| (x.id, x))
+        .collect();
+
+    let stale = stale_links(metadata, &bug_links, &run_tests, &fx_statuses, &bugs);
+    info!("Found {} stale metadata links", stale.len());
+    write_stale_links(Path::new("../docs/stale-metadata.csv"), &stale)?;
+
+    write_bug_index(
+        Path::new("../docs/bug-index.csv"),
+        metadata,
+        &bug_links,
+        &statuses,
+    )?;
+
+    write_triage_summary(
+        Path::new("../docs/triage-summary.csv"),
+        &wpt.products,
+        metadata,
+        &bug_links,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(bug: u64) -> MetadataEntry {
+        MetadataEntry {
+            product: "firefox".into(),
+            url: format!("https://bugzilla.mozilla.org/show_bug.cgi?id={}", bug),
+            results: Vec::new(),
+            label: String::new(),
+        }
+    }
+
+    fn status(passes: i64, total: i64) -> LegacyStatus {
+        LegacyStatus { passes, total }
+    }
+
+    #[test]
+    fn stale_link_categories() {
+        let metadata = BTreeMap::from([
+            ("/a/removed.html".to_owned(), vec![link(1)]),
+            ("/a/no-firefox-result.html".to_owned(), vec![link(2)]),
+            ("/a/passing.html".to_owned(), vec![link(3)]),
+            ("/a/fixed.html".to_owned(), vec![link(4)]),
+            ("/a/failing.html".to_owned(), vec![link(5)]),
+        ]);
+        let run_tests = metadata
+            .keys()
+            .filter(|x| *x != "/a/removed.html")
+            .cloned()
+            .collect();
+        let fx_statuses = BTreeMap::from([
+            ("/a/passing.html".to_owned(), status(2, 2)),
+            ("/a/fixed.html".to_owned(), status(1, 2)),
+            ("/a/failing.html".to_owned(), status(0, 1)),
+        ]);
+        let bugs = BTreeMap::from([(
+            4,
+            Bug {
+                id: 4,
+                status: "RESOLVED".into(),
+                resolution: "FIXED".into(),
+                ..Default::default()
+            },
+        )]);
+
+        let stale = stale_links(
+            &metadata,
+            &BugLinkParser::new(),
+            &run_tests,
+            &fx_statuses,
+            &bugs,
+        );
+        let categories = stale
+            .iter()
+            .map(|x| (x.category, x.test))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            categories,
+            [
+                (StaleCategory::FixedButFailing, "/a/fixed.html"),
+                (StaleCategory::PassingButLinked, "/a/passing.html"),
+                (StaleCategory::TestNoLongerExists, "/a/removed.html"),
+            ]
+        );
+    }
+}
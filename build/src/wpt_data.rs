@@ -0,0 +1,47 @@
+use crate::interop::{get_channel_runs, get_metadata, metadata_products};
+use crate::network;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::rc::Rc;
+use wptfyi::interop::BrowserChannel;
+use wptfyi::metadata::MetadataEntry;
+use wptfyi::{result, Wptfyi};
+
+/// wpt.fyi data used by the interop, triage and team outputs, so that it's
+/// only downloaded once per build
+pub struct WptData {
+    pub client: reqwest::blocking::Client,
+    pub fyi: Wptfyi,
+    pub products: Vec<String>,
+    pub metadata: BTreeMap<String, Vec<MetadataEntry>>,
+    /// Latest complete runs for each channel that's been requested
+    channel_runs: RefCell<BTreeMap<BrowserChannel, Rc<Vec<result::Run>>>>,
+}
+
+impl WptData {
+    pub fn fetch() -> Result<WptData> {
+        let client = network::client()?;
+        let fyi = Wptfyi::new(None);
+        let products = metadata_products(Path::new("../docs/metadata_products.json"))?;
+        let metadata = get_metadata(&fyi, &client, &products)?;
+        Ok(WptData {
+            client,
+            fyi,
+            products,
+            metadata,
+            channel_runs: RefCell::new(BTreeMap::new()),
+        })
+    }
+
+    /// The latest complete set of runs for a channel, see get_channel_runs
+    pub fn channel_runs(&self, channel: BrowserChannel) -> Result<Rc<Vec<result::Run>>> {
+        if let Some(runs) = self.channel_runs.borrow().get(&channel) {
+            return Ok(runs.clone());
+        }
+        let runs = Rc::new(get_channel_runs(&self.fyi, &self.client, channel)?);
+        self.channel_runs.borrow_mut().insert(channel, runs.clone());
+        Ok(runs)
+    }
+}
@@ -50,19 +50,14 @@ def main():
         # If this process fails don't worry
         pass
 
-    try:
-        git("diff", "--exit-code", "--quiet", "--", "docs/",
-            expect_error=True)
-    except subprocess.CalledProcessError:
-        has_changes = True
-    else:
-        has_changes = False
+    # Include untracked files so that newly created outputs get committed
+    has_changes = bool(git("status", "--porcelain", "--", "docs/").strip())
 
     if not has_changes:
         log("INFO: Build didn't change any data files")
         return
 
-    git("add", "-u", "--", "docs/")
+    git("add", "--", "docs/")
     git("commit", "-m", "Update data")
 
     remote_url = "https://%s@github.com/%s" % (os.environ["DEPLOY_TOKEN"],
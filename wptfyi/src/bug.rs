@@ -0,0 +1,308 @@
+use std::fmt::Display;
+use url::{ParseError, Url};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tracker {
+    Bugzilla,
+    Chromium,
+    WebKit,
+    /// GitHub issues for the given owner/repo
+    GitHub(String),
+}
+
+/// A reference to a bug in one of the known issue trackers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BugRef {
+    pub tracker: Tracker,
+    pub id: u64,
+}
+
+impl BugRef {
+    pub fn new(tracker: Tracker, id: u64) -> BugRef {
+        BugRef { tracker, id }
+    }
+
+    /// Canonical URL for the bug
+    pub fn url(&self) -> String {
+        match &self.tracker {
+            Tracker::Bugzilla => {
+                format!("https://bugzilla.mozilla.org/show_bug.cgi?id={}", self.id)
+            }
+            Tracker::Chromium => format!("https://issues.chromium.org/issues/{}", self.id),
+            Tracker::WebKit => format!("https://bugs.webkit.org/show_bug.cgi?id={}", self.id),
+            Tracker::GitHub(repo) => format!("https://github.com/{}/issues/{}", repo, self.id),
+        }
+    }
+}
+
+impl Display for BugRef {
+    /// Bugzilla bugs are written as a bare id, other trackers get a prefix
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.tracker {
+            Tracker::Bugzilla => write!(f, "{}", self.id),
+            Tracker::Chromium => write!(f, "crbug.com/{}", self.id),
+            Tracker::WebKit => write!(f, "webkit.org/b/{}", self.id),
+            Tracker::GitHub(repo) => write!(f, "{}#{}", repo, self.id),
+        }
+    }
+}
+
+/// Recogniser for the links used by a single issue tracker
+pub trait BugTracker {
+    fn bug_ref(&self, url: &Url) -> Option<BugRef>;
+
+    /// Link substrings that identify this tracker in a wpt.fyi link search
+    fn link_patterns(&self) -> &'static [&'static str];
+}
+
+fn query_id(url: &Url) -> Option<u64> {
+    url.query_pairs()
+        .find(|(key, _)| key == "id")
+        .and_then(|(_, id)| id.parse().ok())
+}
+
+fn path_segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|segments| segments.filter(|x| !x.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+/// bugzilla.mozilla.org and the bugzil.la shortener
+pub struct Bugzilla;
+
+impl BugTracker for Bugzilla {
+    fn bug_ref(&self, url: &Url) -> Option<BugRef> {
+        let id = match (url.host_str()?, path_segments(url).as_slice()) {
+            ("bugzilla.mozilla.org", ["show_bug.cgi"]) => query_id(url),
+            ("bugzilla.mozilla.org", ["bug.cgi"]) => query_id(url),
+            ("bugzilla.mozilla.org", [id]) => id.parse().ok(),
+            ("bugzilla.mozilla.org", ["bug.cgi", id]) => id.parse().ok(),
+            ("bugzil.la", [id]) => id.parse().ok(),
+            _ => None,
+        }?;
+        Some(BugRef::new(Tracker::Bugzilla, id))
+    }
+
+    fn link_patterns(&self) -> &'static [&'static str] {
+        &["bugzilla.mozilla.org", "bugzil.la"]
+    }
+}
+
+/// crbug.com, bugs.chromium.org and issues.chromium.org
+pub struct Chromium;
+
+impl BugTracker for Chromium {
+    fn bug_ref(&self, url: &Url) -> Option<BugRef> {
+        let id = match (url.host_str()?, path_segments(url).as_slice()) {
+            ("crbug.com", [id]) => id.parse().ok(),
+            // Other projects such as V8 have their own crbug.com/<project>/ links
+            ("crbug.com", ["chromium", id]) => id.parse().ok(),
+            ("bugs.chromium.org", [_, _, "issues", "detail"]) => query_id(url),
+            ("issues.chromium.org", ["issues", id]) => id.parse().ok(),
+            ("issues.chromium.org", [id]) => id.parse().ok(),
+            _ => None,
+        }?;
+        Some(BugRef::new(Tracker::Chromium, id))
+    }
+
+    fn link_patterns(&self) -> &'static [&'static str] {
+        &["crbug.com", "bugs.chromium.org", "issues.chromium.org"]
+    }
+}
+
+/// bugs.webkit.org and the webkit.org/b/ shortener
+pub struct WebKit;
+
+impl BugTracker for WebKit {
+    fn bug_ref(&self, url: &Url) -> Option<BugRef> {
+        let id = match (url.host_str()?, path_segments(url).as_slice()) {
+            ("bugs.webkit.org", ["show_bug.cgi"]) => query_id(url),
+            ("webkit.org", ["b", id]) => id.parse().ok(),
+            _ => None,
+        }?;
+        Some(BugRef::new(Tracker::WebKit, id))
+    }
+
+    fn link_patterns(&self) -> &'static [&'static str] {
+        &["bugs.webkit.org", "webkit.org/b/"]
+    }
+}
+
+/// Issues in any GitHub repository
+pub struct GitHub;
+
+impl BugTracker for GitHub {
+    fn bug_ref(&self, url: &Url) -> Option<BugRef> {
+        match (url.host_str()?, path_segments(url).as_slice()) {
+            ("github.com", [owner, repo, "issues", id]) => Some(BugRef::new(
+                Tracker::GitHub(format!("{}/{}", owner, repo)),
+                id.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+
+    fn link_patterns(&self) -> &'static [&'static str] {
+        &["github.com"]
+    }
+}
+
+/// Turns metadata link URLs into bug references using a list of trackers
+pub struct BugLinkParser {
+    trackers: Vec<Box<dyn BugTracker>>,
+}
+
+impl BugLinkParser {
+    /// Create a parser that recognises all the built-in trackers
+    pub fn new() -> BugLinkParser {
+        let mut parser = BugLinkParser::empty();
+        parser.add_tracker(Box::new(Bugzilla));
+        parser.add_tracker(Box::new(Chromium));
+        parser.add_tracker(Box::new(WebKit));
+        parser.add_tracker(Box::new(GitHub));
+        parser
+    }
+
+    /// Create a parser that doesn't recognise any tracker
+    pub fn empty() -> BugLinkParser {
+        BugLinkParser {
+            trackers: Vec::new(),
+        }
+    }
+
+    pub fn add_tracker(&mut self, tracker: Box<dyn BugTracker>) {
+        self.trackers.push(tracker);
+    }
+
+    /// Parse a link, which may not have a scheme e.g. crbug.com/123
+    pub fn parse(&self, url: &str) -> Option<BugRef> {
+        let url = url.trim();
+        let url = match Url::parse(url) {
+            Ok(url) => url,
+            Err(ParseError::RelativeUrlWithoutBase) => {
+                Url::parse(&format!("https://{}", url)).ok()?
+            }
+            Err(_) => return None,
+        };
+        self.trackers.iter().find_map(|x| x.bug_ref(&url))
+    }
+
+    /// Link patterns for all the trackers
+    pub fn link_patterns(&self) -> Vec<&'static str> {
+        self.trackers
+            .iter()
+            .flat_map(|x| x.link_patterns().iter().copied())
+            .collect()
+    }
+}
+
+impl Default for BugLinkParser {
+    fn default() -> BugLinkParser {
+        BugLinkParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(url: &str) -> Option<BugRef> {
+        BugLinkParser::new().parse(url)
+    }
+
+    #[test]
+    fn bugzilla_links() {
+        let expected = Some(BugRef::new(Tracker::Bugzilla, 1234567));
+        for url in [
+            "https://bugzilla.mozilla.org/show_bug.cgi?id=1234567",
+            "https://bugzilla.mozilla.org/bug.cgi?id=1234567",
+            "https://bugzilla.mozilla.org/1234567",
+            "https://bugzilla.mozilla.org/bug.cgi/1234567",
+            "https://bugzil.la/1234567",
+            "bugzilla.mozilla.org/show_bug.cgi?id=1234567",
+            "bugzil.la/1234567",
+        ] {
+            assert_eq!(parse(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn chromium_links() {
+        let expected = Some(BugRef::new(Tracker::Chromium, 40123456));
+        for url in [
+            "https://crbug.com/40123456",
+            "https://crbug.com/chromium/40123456",
+            "https://bugs.chromium.org/p/chromium/issues/detail?id=40123456",
+            "https://issues.chromium.org/issues/40123456",
+            "https://issues.chromium.org/40123456",
+            "crbug.com/40123456",
+            "crbug.com/chromium/40123456",
+            "issues.chromium.org/issues/40123456",
+        ] {
+            assert_eq!(parse(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn other_crbug_projects() {
+        assert_eq!(parse("https://crbug.com/v8/12345"), None);
+        assert_eq!(parse("crbug.com/skia/12345"), None);
+    }
+
+    #[test]
+    fn webkit_links() {
+        let expected = Some(BugRef::new(Tracker::WebKit, 234567));
+        for url in [
+            "https://bugs.webkit.org/show_bug.cgi?id=234567",
+            "https://webkit.org/b/234567",
+            "webkit.org/b/234567",
+            "bugs.webkit.org/show_bug.cgi?id=234567",
+        ] {
+            assert_eq!(parse(url), expected, "{}", url);
+        }
+    }
+
+    #[test]
+    fn github_links() {
+        let expected = Some(BugRef::new(
+            Tracker::GitHub("web-platform-tests/wpt".into()),
+            12345,
+        ));
+        for url in [
+            "https://github.com/web-platform-tests/wpt/issues/12345",
+            "github.com/web-platform-tests/wpt/issues/12345",
+        ] {
+            assert_eq!(parse(url), expected, "{}", url);
+        }
+        assert_eq!(
+            parse("https://github.com/web-platform-tests/wpt/pull/12345"),
+            None
+        );
+    }
+
+    #[test]
+    fn unrecognised_links() {
+        for url in [
+            "",
+            "not a link",
+            "https://example.org/show_bug.cgi?id=1",
+            "https://bugzilla.mozilla.org/show_bug.cgi?id=abc",
+            "https://crbug.com/",
+        ] {
+            assert_eq!(parse(url), None, "{}", url);
+        }
+    }
+
+    #[test]
+    fn empty_parser() {
+        assert_eq!(
+            BugLinkParser::empty().parse("https://crbug.com/40123456"),
+            None
+        );
+        assert!(BugLinkParser::empty().link_patterns().is_empty());
+        assert_eq!(
+            BugLinkParser::new().link_patterns(),
+            BugLinkParser::default().link_patterns()
+        );
+    }
+}
@@ -7,10 +7,30 @@ use url::Url;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct InteropMetadata {
-    valid_years: Vec<String>,
-    valid_mobile_years: Vec<String>,
+    pub valid_years: Vec<String>,
+    pub valid_mobile_years: Vec<String>,
     #[serde(flatten)]
-    years: BTreeMap<String, YearData>,
+    pub years: BTreeMap<String, YearData>,
+}
+
+impl InteropMetadata {
+    /// Years that have interop scores
+    pub fn interop_years(&self) -> Vec<u64> {
+        parse_years(&self.valid_years)
+    }
+
+    /// Years that have mobile interop scores
+    pub fn mobile_years(&self) -> Vec<u64> {
+        parse_years(&self.valid_mobile_years)
+    }
+
+    pub fn is_mobile_year(&self, year: u64) -> bool {
+        self.mobile_years().contains(&year)
+    }
+}
+
+fn parse_years(years: &[String]) -> Vec<u64> {
+    years.iter().filter_map(|x| x.parse().ok()).collect()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -74,9 +94,12 @@ impl InteropData {
 }
 
 pub fn parse(json: &str) -> Result<BTreeMap<String, YearData>, Error> {
+    Ok(parse_metadata(json)?.years)
+}
+
+pub fn parse_metadata(json: &str) -> Result<InteropMetadata, Error> {
     debug!("Parsing:\n{}", json);
-    let metadata: InteropMetadata = serde_json::from_str(json)?;
-    Ok(metadata.years)
+    Ok(serde_json::from_str(json)?)
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -100,6 +123,7 @@ pub struct Category {
     pub labels: Vec<String>,
 }
 
+#[derive(Default)]
 pub struct CategoryData {}
 
 impl CategoryData {
@@ -120,10 +144,44 @@ pub fn parse_categories(json: &str) -> Result<BTreeMap<String, Categories>, Erro
     Ok(serde_json::from_str(json)?)
 }
 
-#[derive(Debug, Copy, Clone)]
+/// Desktop browsers that take part in interop, with Firefox first
+pub const DESKTOP_BROWSERS: [&str; 3] = ["firefox", "chrome", "safari"];
+
+/// Mobile browsers that take part in interop, with Firefox first
+pub const MOBILE_BROWSERS: [&str; 3] = ["firefox_android", "chrome_android", "safari_ios"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum BrowserChannel {
     Stable,
     Experimental,
+    MobileStable,
+    MobileExperimental,
+}
+
+impl BrowserChannel {
+    pub fn is_mobile(&self) -> bool {
+        matches!(
+            self,
+            BrowserChannel::MobileStable | BrowserChannel::MobileExperimental
+        )
+    }
+
+    /// Channel name used for wpt.fyi products
+    pub fn run_channel(&self) -> &'static str {
+        match self {
+            BrowserChannel::Stable | BrowserChannel::MobileStable => "stable",
+            BrowserChannel::Experimental | BrowserChannel::MobileExperimental => "experimental",
+        }
+    }
+
+    /// wpt.fyi product names scored on this channel, with Firefox first
+    pub fn browsers(&self) -> &'static [&'static str] {
+        if self.is_mobile() {
+            &MOBILE_BROWSERS
+        } else {
+            &DESKTOP_BROWSERS
+        }
+    }
 }
 
 impl Display for BrowserChannel {
@@ -131,6 +189,8 @@ impl Display for BrowserChannel {
         f.write_str(match self {
             BrowserChannel::Stable => "stable",
             BrowserChannel::Experimental => "experimental",
+            BrowserChannel::MobileStable => "mobile-stable",
+            BrowserChannel::MobileExperimental => "mobile-experimental",
         })
     }
 }
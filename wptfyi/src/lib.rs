@@ -1,3 +1,4 @@
+pub mod bug;
 pub mod error;
 pub mod interop;
 pub mod metadata;
@@ -1,6 +1,8 @@
+use crate::bug::{BugLinkParser, BugRef};
 use crate::error::Error;
+use crate::result::Status;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use url::Url;
 
 #[derive(Debug, Default)]
@@ -44,13 +46,65 @@ pub struct MetadataEntry {
     pub label: String,
 }
 
+impl MetadataEntry {
+    /// The bug this entry links to, if the link is to a known tracker
+    pub fn bug(&self, parser: &BugLinkParser) -> Option<BugRef> {
+        parser.parse(&self.url)
+    }
+
+    /// Whether this entry is a triage link rather than just a label
+    pub fn is_link(&self) -> bool {
+        !self.url.is_empty()
+    }
+
+    pub fn is_label(&self) -> bool {
+        !self.label.is_empty()
+    }
+
+    /// Whether the link only applies to specific subtests or statuses
+    pub fn is_scoped(&self) -> bool {
+        !self.results.is_empty()
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct MetadataResult {
     pub subtest: Option<String>,
-    // TODO: this should map onto a Status
     pub status: Option<u64>,
 }
 
+impl MetadataResult {
+    /// The status this result applies to, using wpt.fyi's numeric status values
+    pub fn status(&self) -> Option<Status> {
+        match self.status? {
+            1 => Some(Status::Pass),
+            2 => Some(Status::Ok),
+            3 => Some(Status::Error),
+            4 => Some(Status::Timeout),
+            5 => Some(Status::NotRun),
+            6 => Some(Status::Fail),
+            7 => Some(Status::Crash),
+            8 => Some(Status::Skip),
+            9 => Some(Status::Assert),
+            10 => Some(Status::PreconditionFailed),
+            _ => None,
+        }
+    }
+}
+
 pub fn parse(json: &str) -> Result<BTreeMap<String, Vec<MetadataEntry>>, Error> {
     Ok(serde_json::from_str(json)?)
 }
+
+/// Get the labels applied to each test
+pub fn test_labels(
+    metadata: &BTreeMap<String, Vec<MetadataEntry>>,
+) -> BTreeMap<&str, BTreeSet<&str>> {
+    let mut rv: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (test, entries) in metadata.iter() {
+        for entry in entries.iter().filter(|x| x.is_label()) {
+            rv.entry(test).or_default().insert(&entry.label);
+        }
+    }
+    rv
+}
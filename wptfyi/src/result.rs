@@ -1,8 +1,13 @@
+use crate::error::Error;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
 use time::serde::iso8601;
 use time::OffsetDateTime;
+use url::Url;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Status {
     Ok,
@@ -12,9 +17,36 @@ pub enum Status {
     Timeout,
     NotRun,
     Crash,
+    Skip,
+    Assert,
+    #[serde(rename = "PRECONDITION_FAILED")]
+    PreconditionFailed,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Status {
+    pub fn is_pass(&self) -> bool {
+        matches!(self, Status::Ok | Status::Pass)
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Status::Ok => "OK",
+            Status::Pass => "PASS",
+            Status::Fail => "FAIL",
+            Status::Error => "ERROR",
+            Status::Timeout => "TIMEOUT",
+            Status::NotRun => "NOTRUN",
+            Status::Crash => "CRASH",
+            Status::Skip => "SKIP",
+            Status::Assert => "ASSERT",
+            Status::PreconditionFailed => "PRECONDITION_FAILED",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Run {
     pub id: i64,
     pub browser_name: String,
@@ -34,6 +66,21 @@ pub struct Run {
     pub labels: Vec<String>,
 }
 
+impl Run {
+    /// URL of the file holding the full results of a single test in this run
+    pub fn test_results_url(&self, test: &str) -> Option<Url> {
+        let base = self
+            .results_url
+            .strip_suffix("-summary_v2.json.gz")
+            .or_else(|| self.results_url.strip_suffix("-summary.json.gz"))?;
+        let mut url = Url::parse(base).ok()?;
+        url.path_segments_mut()
+            .ok()?
+            .extend(test.trim_start_matches('/').split('/'));
+        Some(url)
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchData {
     pub runs: Vec<Run>,
@@ -46,8 +93,61 @@ pub struct SearchResult {
     pub legacy_status: Vec<LegacyStatus>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct LegacyStatus {
     pub passes: i64,
     pub total: i64,
 }
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TestResults {
+    pub test: String,
+    pub status: Status,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub subtests: Vec<SubtestResult>,
+}
+
+impl TestResults {
+    pub fn subtest(&self, name: &str) -> Option<&SubtestResult> {
+        self.subtests.iter().find(|x| x.name == name)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SubtestResult {
+    pub name: String,
+    pub status: Status,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+pub fn parse_test_results(json: &str) -> Result<TestResults, Error> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Get the names of all the tests in a run summary, as found at
+/// Run::results_url
+pub fn parse_summary_tests(json: &str) -> Result<BTreeSet<String>, Error> {
+    let summary: BTreeMap<String, IgnoredAny> = serde_json::from_str(json)?;
+    Ok(summary.into_keys().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_tests() {
+        let v1 = r#"{"/a/b.html": [1, 2], "/a/c.html?x": [0, 1]}"#;
+        let v2 =
+            r#"{"/a/b.html": {"s": "O", "c": [1, 2]}, "/a/c.html?x": {"s": "E", "c": [0, 1]}}"#;
+        for summary in [v1, v2] {
+            assert_eq!(
+                parse_summary_tests(summary).unwrap(),
+                BTreeSet::from(["/a/b.html".to_owned(), "/a/c.html?x".to_owned()])
+            );
+        }
+    }
+}
@@ -58,7 +58,7 @@ pub fn runs_by_commit(runs: &[Run]) -> HashMap<String, Vec<&Run>> {
     for run in runs.iter() {
         runs_by_commit
             .entry(run.full_revision_hash.to_owned())
-            .or_insert_with(|| vec![])
+            .or_default()
             .push(run);
     }
     runs_by_commit
@@ -18,6 +18,7 @@ pub enum Clause {
     Result(ResultClause),
     Link(LinkClause),
     Label(LabelClause),
+    Pattern(PatternClause),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -63,6 +64,12 @@ pub struct LabelClause {
     pub label: String,
 }
 
+/// Matches tests whose path contains the pattern
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PatternClause {
+    pub pattern: String,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchBody {
     #[serde(flatten)]